@@ -1,8 +1,12 @@
 use std::fs;
 use std::fs::File;
+use std::path::PathBuf;
 use std::str;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
 use clap::Args;
 use clap::Parser;
@@ -10,6 +14,13 @@ use clap::Subcommand;
 use daemonize::Daemonize;
 use futures::lock::Mutex;
 use libc::kill;
+use libc::SIGKILL;
+use libc::SIGTERM;
+use rings_node::bootstrap::Bootstrap;
+use rings_node::config::Config;
+use rings_node::config::ConfigArgs;
+use rings_node::ethereum::Registry;
+use rings_node::logger::LogConfig;
 use rings_node::logger::LogLevel;
 use rings_node::logger::Logger;
 use rings_node::prelude::rings_core::async_trait;
@@ -23,13 +34,21 @@ use rings_node::prelude::rings_core::message::MaybeEncrypted;
 use rings_node::prelude::rings_core::message::Message;
 use rings_node::prelude::rings_core::message::MessageHandler;
 use rings_node::prelude::rings_core::message::MessagePayload;
+use rings_node::prelude::rings_core::message::TurnCredentialOperator;
 use rings_node::prelude::rings_core::prelude::url;
 use rings_node::prelude::rings_core::session::SessionManager;
+use rings_node::prelude::rings_core::swarm::PendingTransportCollector;
 use rings_node::prelude::rings_core::swarm::Swarm;
+use rings_node::prelude::rings_core::swarm::TCollect;
+use rings_node::prelude::rings_core::swarm::TransportManager;
+use rings_node::prelude::rings_core::turn::TurnCredentialIssuer;
 use rings_node::prelude::rings_core::types::message::MessageListener;
+use rings_node::processor::Processor;
 use rings_node::service::run_service;
-use rings_node::service::run_udp_turn;
-use tokio::signal;
+use rings_node::service::run_turn;
+use rings_node::service::wait_for_signal;
+use rings_node::storage::DhtStorage;
+use tokio::sync::oneshot;
 
 #[derive(Parser, Debug)]
 #[clap(about)]
@@ -44,27 +63,21 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Command {
     Run(Box<RunArgs>),
+    #[clap(subcommand)]
+    Config(ConfigCommand),
     Shutdown(ShutdownArgs),
 }
 
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Validate config file and flags, and print the merged config.
+    Check(Box<ConfigCheck>),
+}
+
 #[derive(Args, Debug)]
 struct RunArgs {
-    #[clap(long, short = 'b', default_value = "127.0.0.1:50000", env)]
-    pub http_addr: String,
-
-    #[clap(long, short = 's', default_value = "stun://stun.l.google.com:19302")]
-    pub ice_server: Vec<String>,
-
-    #[clap(
-        long = "eth",
-        short = 'e',
-        default_value = "http://127.0.0.1:8545",
-        env
-    )]
-    pub eth_endpoint: String,
-
-    #[clap(long = "key", short = 'k', env)]
-    pub eth_key: SecretKey,
+    #[clap(flatten)]
+    pub config_args: ConfigArgs,
 
     #[clap(short = 'd')]
     pub daemonize: bool,
@@ -82,20 +95,20 @@ struct RunArgs {
     pub work_dir: String,
 
     /// STUN server address.
-    #[clap(long, default_value = "3478")]
-    pub turn_port: u16,
+    #[clap(long)]
+    pub turn_port: Option<u16>,
 
     /// STUN publicip.
-    #[clap(long, default_value = "127.0.0.1")]
-    pub public_ip: String,
+    #[clap(long)]
+    pub public_ip: Option<String>,
 
     /// Username.
-    #[clap(long, default_value = "rings")]
-    pub turn_username: String,
+    #[clap(long)]
+    pub turn_username: Option<String>,
 
     /// Password.
-    #[clap(long, default_value = "password")]
-    pub turn_password: String,
+    #[clap(long)]
+    pub turn_password: Option<String>,
 
     /// Realm.
     /// REALM
@@ -103,25 +116,109 @@ struct RunArgs {
     /// Secret Responses. It contains text which meets the grammar for
     /// "realm" as described in RFC 3261, and will thus contain a quoted
     /// string (including the quotes).
-    #[clap(long, default_value = "rings")]
-    pub turn_realm: String,
+    #[clap(long)]
+    pub turn_realm: Option<String>,
+
+    /// TCP port of turn server, TURN over tcp is disabled if not set.
+    #[clap(long)]
+    pub turn_tcp_port: Option<u16>,
+
+    /// TLS port of turn server, `--turn-tls-cert` and `--turn-tls-key` are required with it.
+    #[clap(long)]
+    pub turn_tls_port: Option<u16>,
+
+    /// Certificate chain of turn server in PEM.
+    #[clap(long)]
+    pub turn_tls_cert: Option<PathBuf>,
+
+    /// Private key of turn server in PKCS #8 PEM.
+    #[clap(long)]
+    pub turn_tls_key: Option<PathBuf>,
+
+    /// Secret of time-limited credentials issued to ring members.
+    #[clap(long, env = "RINGS_TURN_SECRET")]
+    pub turn_secret: Option<String>,
 
     #[clap(long)]
     pub without_turn: bool,
 
-    #[clap(long, default_value = "20")]
-    pub stabilize_timeout: usize,
+    /// Seconds to wait for leaving the ring and closing transports on shutdown.
+    #[clap(long, default_value_t = 10)]
+    pub shutdown_timeout: u64,
+}
+
+impl RunArgs {
+    /// Load config and apply turn flags over it.
+    fn load_config(&self) -> anyhow::Result<Config> {
+        let mut config = self.config_args.load()?;
+        let turn = &mut config.turn;
+        if let Some(v) = self.turn_port {
+            turn.port = v;
+        }
+        if let Some(v) = &self.public_ip {
+            turn.public_ip = v.clone();
+        }
+        if let Some(v) = &self.turn_username {
+            turn.username = v.clone();
+        }
+        if let Some(v) = &self.turn_password {
+            turn.password = v.clone();
+        }
+        if let Some(v) = &self.turn_realm {
+            turn.realm = v.clone();
+        }
+        if self.turn_tcp_port.is_some() {
+            turn.tcp_port = self.turn_tcp_port;
+        }
+        if self.turn_tls_port.is_some() {
+            turn.tls_port = self.turn_tls_port;
+        }
+        if self.turn_tls_cert.is_some() {
+            turn.tls_cert = self.turn_tls_cert.clone();
+        }
+        if self.turn_tls_key.is_some() {
+            turn.tls_key = self.turn_tls_key.clone();
+        }
+        if self.turn_secret.is_some() {
+            turn.secret = self.turn_secret.clone();
+        }
+        if self.without_turn {
+            turn.enabled = false;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[derive(Args, Debug)]
+struct ConfigCheck {
+    #[clap(flatten)]
+    pub run_args: RunArgs,
 }
 
 #[derive(Args, Debug)]
 struct ShutdownArgs {
     #[clap(long, short = 'p', default_value = "/tmp/rings-node.pid")]
     pub pid_file: String,
+
+    /// Seconds to wait for the daemon to exit.
+    #[clap(long, short = 't', default_value_t = 15)]
+    pub timeout: u64,
+
+    /// Kill the daemon if it's still running after timeout.
+    #[clap(long)]
+    pub force: bool,
 }
 
-async fn run_jobs(args: &RunArgs) -> anyhow::Result<()> {
-    let key: &SecretKey = &args.eth_key;
+async fn run_jobs(config: &Config, key: &SecretKey, shutdown_timeout: u64) -> anyhow::Result<()> {
     let dht = Arc::new(Mutex::new(PeerRing::new(key.address().into())));
+    let storage = DhtStorage::open(&config.storage).await?;
+    let restored = storage.restore(&*dht.lock().await).await?;
+    log::info!(
+        "restored {} records from {}",
+        restored,
+        config.storage.path.display()
+    );
 
     let (auth, s_key) = SessionManager::gen_unsign_info(
         key.address(),
@@ -131,31 +228,28 @@ async fn run_jobs(args: &RunArgs) -> anyhow::Result<()> {
     let sig = key.sign(&auth.to_string()?).to_vec();
     let session = SessionManager::new(&sig, &auth, &s_key);
 
-    let mut ice_servers = args.ice_server.clone();
-    let turn_server = if !args.without_turn {
+    let mut ice_servers = config.ice_server_urls()?;
+    let turn = &config.turn;
+    let turn_server = if turn.enabled {
         let mut turn_url = url::Url::from_str("turn://0.0.0.0:3567").unwrap();
-        turn_url.set_port(Some(args.turn_port)).unwrap();
-        turn_url.set_username(args.turn_username.as_str()).unwrap();
-        turn_url
-            .set_password(Some(args.turn_password.as_str()))
-            .unwrap();
+        turn_url.set_port(Some(turn.port)).unwrap();
+        turn_url.set_username(turn.username.as_str()).unwrap();
+        turn_url.set_password(Some(turn.password.as_str())).unwrap();
         ice_servers.push(turn_url.to_string());
-        Some(
-            run_udp_turn(
-                args.public_ip.as_str(),
-                args.turn_port,
-                args.turn_username.as_str(),
-                args.turn_password.as_str(),
-                args.turn_realm.as_str(),
-            )
-            .await?,
-        )
+        let server = run_turn(turn).await?;
+        log::info!("turn server is listening on {}", turn.urls().join(", "));
+        Some(server)
     } else {
         None
     };
 
     let ice_servers = ice_servers.join(";");
-    let swarm = Arc::new(Swarm::new(&ice_servers, key.address(), session));
+    let swarm = Arc::new(Swarm::new_with_max_connections(
+        &ice_servers,
+        key.address(),
+        session,
+        config.limits.max_connections,
+    ));
 
     // let listen_event = MessageHandler::new(dht.clone(), swarm.clone());
     let message_callback = MessageCallback {};
@@ -164,39 +258,96 @@ async fn run_jobs(args: &RunArgs) -> anyhow::Result<()> {
         swarm.clone(),
         Box::new(message_callback),
     ));
+    if let (true, Some(secret)) = (turn.enabled, &turn.secret) {
+        listen_event
+            .set_turn_issuer(TurnCredentialIssuer::new(
+                turn.urls(),
+                secret,
+                turn.credential_ttl,
+            ))
+            .await;
+    }
     let stabilization = Arc::new(Stabilization::new(
         dht.clone(),
         swarm.clone(),
-        args.stabilize_timeout,
+        config.stabilize_timeout,
+    ));
+    let collector = Arc::new(PendingTransportCollector::new(
+        swarm.clone(),
+        config.limits.pending_timeout,
+    ));
+    let registry = Registry::new_with_config(config).await?.map(Arc::new);
+    let processor = Processor::from((swarm.clone(), listen_event.clone(), stabilization.clone()))
+        .with_registry(registry);
+    let bootstrap = Arc::new(Bootstrap::new(
+        processor.clone(),
+        config.bootstrap.peers()?,
+        config.bootstrap.retries,
+        config.bootstrap.retry_interval,
+        config.bootstrap.rejoin_interval,
     ));
-    let http_addr = args.http_addr.clone();
+    let service_config = config.clone();
     let listen_event_1 = listen_event.clone();
-    let listen_event_2 = listen_event.clone();
-    let stabilization_1 = stabilization.clone();
     let stabilization_2 = stabilization.clone();
-    let j = tokio::spawn(futures::future::join3(
+    let swarm_1 = swarm.clone();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let j = tokio::spawn(futures::future::join5(
         async {
             listen_event_1.listen().await;
             AnyhowResult::Ok(())
         },
         async {
-            run_service(http_addr, swarm, listen_event_2, stabilization_1).await?;
+            run_service(service_config, processor, async {
+                shutdown_rx.await.ok();
+            })
+            .await?;
             AnyhowResult::Ok(())
         },
         async {
             stabilization_2.wait().await;
             AnyhowResult::Ok(())
         },
+        async {
+            collector.wait().await;
+            AnyhowResult::Ok(())
+        },
+        async {
+            bootstrap.wait().await;
+            AnyhowResult::Ok(())
+        },
     ));
-    signal::ctrl_c().await.expect("failed to listen for event");
+    wait_for_signal().await?;
     println!("\nClosing connection now...");
-    j.abort();
-    if let Some(s) = turn_server {
-        if let Err(e) = s.close().await {
-            println!("close turn_server failed, {}", e);
+    // stop accepting rpc requests first
+    shutdown_tx.send(()).ok();
+    let close = async {
+        if let Err(e) = listen_event.leave().await {
+            log::warn!("failed to announce leave: {}", e);
+        }
+        match storage.save(&*dht.lock().await).await {
+            Ok(n) => log::info!("saved {} records to storage", n),
+            Err(e) => log::warn!("failed to save storage: {}", e),
         }
+        if let Err(e) = swarm_1.close_all_transports().await {
+            log::warn!("failed to close transports: {}", e);
+        }
+        if let Some(s) = turn_server {
+            if let Err(e) = s.close().await {
+                println!("close turn_server failed, {}", e);
+            }
+        }
+    };
+    if tokio::time::timeout(Duration::from_secs(shutdown_timeout), close)
+        .await
+        .is_err()
+    {
+        log::warn!("shutdown timed out after {}s", shutdown_timeout);
     }
-    println!("Server closed");
+    j.abort();
+    println!(
+        "Server closed, {} transports left",
+        swarm_1.get_transport_numbers()
+    );
 
     Ok(())
 }
@@ -227,6 +378,9 @@ impl message::MessageCallback for MessageCallback {
 }
 
 fn run_daemon(args: &RunArgs) -> AnyhowResult<()> {
+    let config = args.load_config()?;
+    // load key before daemonize, passphrase of keystore may be prompted on tty.
+    let key = config.secret_key()?;
     if args.daemonize {
         fs::create_dir_all("/tmp/rings-node")?;
         let stdout = File::create("/tmp/rings-node/info.log")?;
@@ -245,27 +399,70 @@ fn run_daemon(args: &RunArgs) -> AnyhowResult<()> {
         }
     }
     let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async {
-        if let Err(e) = run_jobs(args).await {
-            panic!("{}", e);
+    let result = rt.block_on(run_jobs(&config, &key, args.shutdown_timeout));
+    if args.daemonize {
+        if let Err(e) = fs::remove_file(args.pid_file.as_str()) {
+            log::warn!("failed to remove pid file {}: {}", args.pid_file, e);
         }
-    });
+    }
+    if let Err(e) = result {
+        panic!("{}", e);
+    }
     Ok(())
 }
 
+fn print_config(config: &Config) {
+    println!("Config is valid.");
+    if let Ok(key) = config.secret_key() {
+        println!("Address: {:?}", key.address());
+    }
+    println!("{}", config.redacted());
+}
+
+fn is_running(pid: i32) -> bool {
+    unsafe { kill(pid, 0) == 0 }
+}
+
+/// Send SIGTERM to daemon and wait until it exits.
 fn shutdown_daemon(args: &ShutdownArgs) -> anyhow::Result<()> {
-    let pid: i32 = fs::read_to_string(args.pid_file.as_str())?.parse()?;
-    unsafe {
-        kill(pid, 9);
+    let pid: i32 = fs::read_to_string(args.pid_file.as_str())?.trim().parse()?;
+    if unsafe { kill(pid, SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
     }
-    println!("Killed: {}", pid);
+    println!("Stopping: {}", pid);
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    while is_running(pid) {
+        if Instant::now() >= deadline {
+            if !args.force {
+                anyhow::bail!("{} is still running after {}s", pid, args.timeout);
+            }
+            unsafe {
+                kill(pid, SIGKILL);
+            }
+            println!("Killed: {}", pid);
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    println!("Stopped: {}", pid);
     Ok(())
 }
 
 fn main() {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
-    Logger::init(cli.log_level.into()).expect("log err");
+    let log_config = match &cli.command {
+        // logger is not ready yet, report invalid config on stderr.
+        Command::Run(args) => match args.load_config() {
+            Ok(config) => config.log,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => LogConfig::default(),
+    };
+    Logger::init(cli.log_level.clone(), &log_config).expect("log err");
 
     match cli.command {
         Command::Run(args) => {
@@ -273,9 +470,17 @@ fn main() {
                 panic!("{}", e);
             }
         }
+        Command::Config(ConfigCommand::Check(args)) => match args.run_args.load_config() {
+            Ok(config) => print_config(&config),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
         Command::Shutdown(args) => {
             if let Err(e) = shutdown_daemon(&args) {
-                panic!("{}", e);
+                eprintln!("{}", e);
+                std::process::exit(1);
             }
         }
     };
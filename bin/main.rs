@@ -1,4 +1,5 @@
 #![feature(async_closure)]
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use clap::Args;
@@ -11,12 +12,26 @@ use rings_core::dht::TStabilize;
 use rings_core::ecc::SecretKey;
 use rings_core::message::MessageHandler;
 use rings_core::session::SessionManager;
+use rings_core::swarm::PendingTransportCollector;
 use rings_core::swarm::Swarm;
+use rings_core::swarm::TCollect;
 use rings_core::types::message::MessageListener;
+use rings_node::bootstrap::Bootstrap;
 use rings_node::cli::Client;
+use rings_node::config::Config;
+use rings_node::config::ConfigArgs;
+use rings_node::crawler::CrawlFormat;
+use rings_node::ethereum::Registry;
+use rings_node::jsonrpc::auth::Credential;
+use rings_node::jsonrpc::method::Topic;
+use rings_node::keystore;
+use rings_node::logger::LogConfig;
 use rings_node::logger::LogLevel;
 use rings_node::logger::Logger;
+use rings_node::processor::Processor;
 use rings_node::service::run_service;
+use rings_node::service::wait_for_signal;
+use rings_node::storage::DhtStorage;
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
@@ -34,6 +49,8 @@ enum Command {
     #[clap(about = "daemon")]
     Run(Daemon),
     #[clap(subcommand)]
+    Config(ConfigCommand),
+    #[clap(subcommand)]
     Connect(ConnectCommand),
     #[clap(subcommand)]
     Sdp(SdpCommand),
@@ -42,36 +59,87 @@ enum Command {
     #[clap(subcommand)]
     Pending(PendingCommand),
     Send(Send),
+    #[clap(subcommand)]
+    Storage(StorageCommand),
+    #[clap(subcommand)]
+    SubRing(SubRingCommand),
+    #[clap(about = "Subscribe and print events of node, like `tail -f`.")]
+    Listen(Listen),
+    #[clap(about = "Print routing state, session, transports and storage of node.")]
+    Inspect(Inspect),
+    #[clap(about = "Ping a node through ring, print round trip time.")]
+    Ping(Ping),
+    #[clap(about = "Route a ping to a did through ring, print every hop passed.")]
+    Traceroute(Traceroute),
+    #[clap(about = "Walk successor and finger pointers, print the ring and its inconsistencies.")]
+    Crawl(Crawl),
     NewSecretKey,
+    #[clap(subcommand)]
+    Key(KeyCommand),
 }
 
 #[derive(Args, Debug)]
 #[clap(about)]
 struct Daemon {
-    #[clap(long, short = 'b', default_value = "127.0.0.1:50000", env)]
-    pub http_addr: String,
+    #[clap(flatten)]
+    config_args: ConfigArgs,
+}
 
-    #[clap(
-        long,
-        short = 's',
-        default_value = "stun://stun.l.google.com:19302",
-        env
-    )]
-    pub ice_servers: String,
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+enum ConfigCommand {
+    #[clap(about = "Validate config file and flags, and print the merged config.")]
+    Check(ConfigCheck),
+}
 
-    #[clap(
-        long = "eth",
-        short = 'e',
-        default_value = "http://127.0.0.1:8545",
-        env
-    )]
-    pub eth_endpoint: String,
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+enum KeyCommand {
+    #[clap(about = "Generate a secret key, encrypted into keystore if `--keystore` is given.")]
+    New(KeyNew),
+    #[clap(about = "Encrypt a hex secret key into keystore.")]
+    Import(KeyImport),
+    #[clap(about = "Decrypt keystore and print hex secret key.")]
+    Export(KeyExport),
+}
 
-    #[clap(long = "key", short = 'k', env)]
-    pub eth_key: SecretKey,
+#[derive(Args, Debug)]
+struct KeyNew {
+    /// Path of keystore to write, print the key if not set.
+    #[clap(long)]
+    keystore: Option<PathBuf>,
+    /// File holding passphrase of keystore, prompted if not set.
+    #[clap(long)]
+    password_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct KeyImport {
+    /// Path of keystore to write.
+    #[clap(long)]
+    keystore: PathBuf,
+    /// File holding hex secret key, prompted if not set.
+    #[clap(long)]
+    key_file: Option<PathBuf>,
+    /// File holding passphrase of keystore, prompted if not set.
+    #[clap(long)]
+    password_file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct KeyExport {
+    /// Path of keystore to read.
+    #[clap(long)]
+    keystore: PathBuf,
+    /// File holding passphrase of keystore, prompted if not set.
+    #[clap(long)]
+    password_file: Option<PathBuf>,
+}
 
-    #[clap(long, default_value = "20")]
-    pub stabilize_timeout: usize,
+#[derive(Args, Debug)]
+struct ConfigCheck {
+    #[clap(flatten)]
+    config_args: ConfigArgs,
 }
 
 #[derive(Args, Debug)]
@@ -83,11 +151,27 @@ struct ClientArgs {
         help = "rings-node endpoint url."
     )]
     endpoint_url: String,
+
+    /// Token of jsonrpc endpoint.
+    #[clap(long, env)]
+    rpc_token: Option<String>,
+
+    /// Key of an allowed address to sign requests to jsonrpc endpoint.
+    #[clap(long, env)]
+    rpc_key: Option<SecretKey>,
 }
 
 impl ClientArgs {
+    fn credential(&self) -> Option<Credential> {
+        match (&self.rpc_token, &self.rpc_key) {
+            (Some(token), _) => Some(Credential::Token(token.clone())),
+            (None, Some(key)) => Some(Credential::Key(*key)),
+            (None, None) => None,
+        }
+    }
+
     async fn new_client(&self) -> anyhow::Result<Client> {
-        Client::new(self.endpoint_url.as_str()).await
+        Client::new(self.endpoint_url.as_str(), self.credential()).await
     }
 }
 
@@ -116,6 +200,7 @@ struct ConnectWithAddressArgs {
     #[clap(flatten)]
     client_args: ClientArgs,
 
+    /// Address of node, or its name in registry of the node connected by client.
     #[clap()]
     address: String,
 }
@@ -216,14 +301,113 @@ struct Send {
     text: String,
 }
 
-async fn daemon_run(
-    http_addr: String,
-    key: &SecretKey,
-    stuns: &str,
-    stabilize_timeout: usize,
-) -> anyhow::Result<()> {
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+enum StorageCommand {
+    #[clap(about = "Store a value on DHT, print the did of value.")]
+    Store(StorageStore),
+    #[clap(about = "Fetch a value from DHT with it's did.")]
+    Fetch(StorageFetch),
+}
+
+#[derive(Args, Debug)]
+struct StorageStore {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    value: String,
+}
+
+#[derive(Args, Debug)]
+struct StorageFetch {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    did: String,
+}
+
+#[derive(Subcommand, Debug)]
+#[clap(rename_all = "kebab-case")]
+enum SubRingCommand {
+    Create(SubRingArgs),
+    Join(SubRingArgs),
+    #[clap(about = "List subrings stored on the node.")]
+    List(SubRingList),
+}
+
+#[derive(Args, Debug)]
+struct SubRingArgs {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct SubRingList {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+}
+
+#[derive(Args, Debug)]
+struct Inspect {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+}
+
+#[derive(Args, Debug)]
+struct Ping {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    #[clap(help = "Address of node, or its name in registry.")]
+    address: String,
+}
+
+#[derive(Args, Debug)]
+struct Traceroute {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    did: String,
+}
+
+#[derive(Args, Debug)]
+struct Crawl {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    #[clap(long, help = "Did to start crawling from, default to the node.")]
+    start: Option<String>,
+    #[clap(
+        long,
+        default_value = "256",
+        help = "Stop after this many nodes are found."
+    )]
+    max_nodes: usize,
+    #[clap(long, arg_enum, default_value = "json")]
+    format: CrawlFormat,
+}
+
+#[derive(Args, Debug)]
+struct Listen {
+    #[clap(flatten)]
+    client_args: ClientArgs,
+    #[clap(
+        long = "topic",
+        short = 't',
+        default_values = &["customMessage", "peerEvent", "dhtEvent"],
+        help = "topics to subscribe: customMessage, peerEvent, dhtEvent."
+    )]
+    topics: Vec<String>,
+}
+
+async fn daemon_run(config: Config) -> anyhow::Result<()> {
     // TODO support run daemonize
+    config.validate()?;
+    let key = &config.secret_key()?;
     let dht = Arc::new(Mutex::new(PeerRing::new(key.address().into())));
+    let storage = DhtStorage::open(&config.storage).await?;
+    let restored = storage.restore(&*dht.lock().await).await?;
+    log::info!(
+        "restored {} records from {}",
+        restored,
+        config.storage.path.display()
+    );
     let (auth, temp_key) = SessionManager::gen_unsign_info(
         key.address(),
         Some(rings_core::session::Ttl::Never),
@@ -231,25 +415,58 @@ async fn daemon_run(
     )?;
     let sig = key.sign(&auth.to_string()?).to_vec();
     let session = SessionManager::new(&sig, &auth, &temp_key);
-    let swarm = Arc::new(Swarm::new(stuns, key.address(), session.clone()));
+    let swarm = Arc::new(Swarm::new_with_max_connections(
+        config.ice_servers_str()?.as_str(),
+        key.address(),
+        session.clone(),
+        config.limits.max_connections,
+    ));
     let listen_event = Arc::new(MessageHandler::new(dht.clone(), swarm.clone()));
     let stabilize = Arc::new(Stabilization::new(
         dht.clone(),
         swarm.clone(),
-        stabilize_timeout,
+        config.stabilize_timeout,
+    ));
+    let collector = Arc::new(PendingTransportCollector::new(
+        swarm.clone(),
+        config.limits.pending_timeout,
+    ));
+    let registry = Registry::new_with_config(&config).await?.map(Arc::new);
+    let processor = Processor::from((swarm.clone(), listen_event.clone(), stabilize.clone()))
+        .with_registry(registry);
+    let bootstrap = Arc::new(Bootstrap::new(
+        processor.clone(),
+        config.bootstrap.peers()?,
+        config.bootstrap.retries,
+        config.bootstrap.retry_interval,
+        config.bootstrap.rejoin_interval,
     ));
-    let swarm_clone = swarm.clone();
 
-    let (_, _, _) = futures::join!(
+    let jobs = futures::future::join4(
         listen_event.clone().listen(),
-        run_service(
-            http_addr.to_owned(),
-            swarm_clone,
-            listen_event,
-            stabilize.clone()
-        ),
-        stabilize.wait(),
+        stabilize.clone().wait(),
+        collector.wait(),
+        bootstrap.wait(),
     );
+    let shutdown = async {
+        if let Err(e) = wait_for_signal().await {
+            log::error!("failed to listen for signals: {}", e);
+        }
+    };
+    tokio::select! {
+        r = run_service(config.clone(), processor, shutdown) => r?,
+        _ = jobs => {},
+    }
+
+    println!("\nClosing connection now...");
+    if let Err(e) = listen_event.leave().await {
+        log::warn!("failed to announce leave: {}", e);
+    }
+    let saved = storage.save(&*dht.lock().await).await?;
+    log::info!("saved {} records to storage", saved);
+    if let Err(e) = swarm.close_all_transports().await {
+        log::warn!("failed to close transports: {}", e);
+    }
 
     Ok(())
 }
@@ -258,17 +475,21 @@ async fn daemon_run(
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
-    Logger::init(cli.log_level.into())?;
+    let log_config = match &cli.command {
+        Command::Run(args) => args.config_args.load()?.log,
+        _ => LogConfig::default(),
+    };
+    Logger::init(cli.log_level, &log_config)?;
 
     if let Err(e) = match cli.command {
-        Command::Run(args) => {
-            daemon_run(
-                args.http_addr,
-                &args.eth_key,
-                args.ice_servers.as_str(),
-                args.stabilize_timeout,
-            )
-            .await
+        Command::Run(args) => daemon_run(args.config_args.load()?).await,
+        Command::Config(ConfigCommand::Check(args)) => {
+            let config = args.config_args.load()?;
+            config.validate()?;
+            println!("Config is valid.");
+            println!("Address: {:?}", config.secret_key()?.address());
+            println!("{}", config.redacted());
+            Ok(())
         }
         Command::Connect(ConnectCommand::Node(args)) => {
             args.client_args
@@ -360,6 +581,130 @@ async fn main() -> anyhow::Result<()> {
                 .display();
             Ok(())
         }
+        Command::Storage(StorageCommand::Store(args)) => {
+            args.client_args
+                .new_client()
+                .await?
+                .store_value(args.value.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Storage(StorageCommand::Fetch(args)) => {
+            args.client_args
+                .new_client()
+                .await?
+                .fetch_value(args.did.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::SubRing(SubRingCommand::Create(args)) => {
+            args.client_args
+                .new_client()
+                .await?
+                .create_subring(args.name.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::SubRing(SubRingCommand::Join(args)) => {
+            args.client_args
+                .new_client()
+                .await?
+                .join_subring(args.name.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::SubRing(SubRingCommand::List(args)) => {
+            args.client_args
+                .new_client()
+                .await?
+                .list_subrings()
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Listen(args) => {
+            let topics = args
+                .topics
+                .iter()
+                .map(|t| Topic::try_from(t.as_str()))
+                .collect::<Result<Vec<_>, _>>()?;
+            args.client_args
+                .new_client()
+                .await?
+                .listen(&topics)
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Inspect(args) => {
+            args.client_args
+                .new_client()
+                .await?
+                .inspect()
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Ping(args) => {
+            args.client_args
+                .new_client()
+                .await?
+                .ping(args.address.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Crawl(args) => {
+            args.client_args
+                .new_client()
+                .await?
+                .crawl(args.start.as_deref(), args.max_nodes, args.format)
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Traceroute(args) => {
+            args.client_args
+                .new_client()
+                .await?
+                .traceroute(args.did.as_str())
+                .await?
+                .display();
+            Ok(())
+        }
+        Command::Key(KeyCommand::New(args)) => {
+            let key = SecretKey::random();
+            match args.keystore {
+                Some(path) => {
+                    let password = keystore::read_new_password(args.password_file.as_deref())?;
+                    keystore::encrypt(&key, &path, &password)?;
+                    println!("Address: {:?}", key.address());
+                    println!("Keystore: {}", path.display());
+                }
+                None => println!("New secretKey: {}", key.to_string()),
+            }
+            Ok(())
+        }
+        Command::Key(KeyCommand::Import(args)) => {
+            let hex = keystore::read_password(args.key_file.as_deref(), "Secret key: ")?;
+            let key = SecretKey::try_from(hex.trim())?;
+            let password = keystore::read_new_password(args.password_file.as_deref())?;
+            keystore::encrypt(&key, &args.keystore, &password)?;
+            println!("Address: {:?}", key.address());
+            println!("Keystore: {}", args.keystore.display());
+            Ok(())
+        }
+        Command::Key(KeyCommand::Export(args)) => {
+            let password =
+                keystore::read_password(args.password_file.as_deref(), "Passphrase of keystore: ")?;
+            let key = keystore::decrypt(&args.keystore, &password)?;
+            println!("{}", key.to_string());
+            Ok(())
+        }
         Command::NewSecretKey => {
             let k = SecretKey::random();
             println!("New secretKey: {}", k.to_string());
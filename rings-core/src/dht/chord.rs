@@ -169,6 +169,16 @@ impl PeerRing {
     pub fn number_of_fingers(&self) -> usize {
         self.finger.len()
     }
+
+    /// Check if id is one of successors or the predecessor
+    pub fn is_neighbour(&self, id: Did) -> bool {
+        self.predecessor == Some(id) || self.successor.list().contains(&id)
+    }
+
+    /// Check if id is in finger table
+    pub fn is_finger(&self, id: Did) -> bool {
+        self.finger.contains(&Some(id))
+    }
 }
 
 impl Chord<PeerRingAction> for PeerRing {
@@ -381,6 +391,28 @@ mod tests {
     use super::*;
     use crate::ecc::SecretKey;
 
+    #[test]
+    fn test_chord_neighbour_and_finger() {
+        let a = Did::from_str("0x00E807fcc88dD319270493fB2e822e388Fe36ab0").unwrap();
+        let b = Did::from_str("0x119999cf1046e68e36E1aA2E0E07105eDDD1f08E").unwrap();
+        let c = Did::from_str("0xccffee254729296a45a3885639AC7E10F9d54979").unwrap();
+        let d = Did::from_str("0xffffee254729296a45a3885639AC7E10F9d54979").unwrap();
+
+        let mut node_a = PeerRing::new_with_config(a, 1);
+        node_a.join(b);
+        node_a.join(c);
+        node_a.predecessor = Some(d);
+        // only the closest one is kept as successor
+        assert!(node_a.is_neighbour(b));
+        assert!(!node_a.is_neighbour(c));
+        assert!(node_a.is_neighbour(d));
+        assert!(node_a.is_finger(b));
+        assert!(node_a.is_finger(c));
+        assert!(!node_a.is_finger(d));
+        node_a.remove(c);
+        assert!(!node_a.is_finger(c));
+    }
+
     #[test]
     fn test_chord_finger() {
         let a = Did::from_str("0x00E807fcc88dD319270493fB2e822e388Fe36ab0").unwrap();
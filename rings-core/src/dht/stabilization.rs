@@ -88,6 +88,7 @@ impl Stabilization {
     }
 
     pub async fn stabilize(&self) -> Result<()> {
+        self.swarm.metrics().inc_stabilization_rounds();
         self.notify_predecessor().await?;
         self.fix_fingers().await?;
         Ok(())
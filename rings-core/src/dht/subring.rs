@@ -16,6 +16,7 @@ use crate::dht::Did;
 use crate::ecc::HashStr;
 use crate::err::Error;
 use crate::err::Result;
+use crate::message::Encoder;
 
 /// A SubRing is a full functional Ring, but with a name and it's finger table can be
 /// stored on Main Rings DHT, For a SubRing, it's virtual address is `sha1(name)`
@@ -72,6 +73,15 @@ impl SubRingManager<PeerRingAction> for PeerRing {
         Ok(())
     }
 
+    fn list_subrings(&self) -> Vec<SubRing> {
+        self.storage
+            .values()
+            .into_iter()
+            .filter(|vn| vn.kind == VNodeType::SubRing)
+            .filter_map(|vn| vn.try_into().ok())
+            .collect()
+    }
+
     fn get_subring_by_name(&self, name: &str) -> Option<Result<SubRing>> {
         let address: HashStr = name.to_owned().into();
         // trans Result to Option here
@@ -139,7 +149,7 @@ impl TryFrom<SubRing> for VirtualNode {
         let data = serde_json::to_string(&ring).map_err(|_| Error::SerializeToString)?;
         Ok(Self {
             address: ring.did,
-            data: vec![data.into()],
+            data: vec![data.encode()?],
             kind: VNodeType::SubRing,
         })
     }
@@ -56,6 +56,28 @@ impl Successor {
     }
 
     pub fn remove(&mut self, id: Did) {
-        self.successors.retain(|v| *v == id);
+        self.successors.retain(|v| *v != id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ecc::SecretKey;
+
+    #[test]
+    fn test_successor_remove() {
+        let id: Did = SecretKey::random().address().into();
+        let mut successor = Successor::new(&id, 3);
+        let id1: Did = SecretKey::random().address().into();
+        let id2: Did = SecretKey::random().address().into();
+        successor.update(id1);
+        successor.update(id2);
+        assert_eq!(successor.list().len(), 2);
+
+        successor.remove(id1);
+        assert_eq!(successor.list(), vec![id2]);
+        successor.remove(id2);
+        assert!(successor.is_none());
     }
 }
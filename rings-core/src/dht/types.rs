@@ -42,6 +42,8 @@ pub trait SubRingManager<A>: ChordStorage<A> {
     fn get_subring_by_name(&self, name: &str) -> Option<Result<SubRing>>;
     /// store a subring to storage
     fn store_subring(&self, subring: &SubRing) -> Result<()>;
+    /// list all subrings in storage
+    fn list_subrings(&self) -> Vec<SubRing>;
     /// get a subring for update
     fn get_subring_for_update(
         &self,
@@ -63,6 +63,9 @@ pub enum Error {
     #[error("Ice server get url without host")]
     IceServerURLMissHost,
 
+    #[error("Ice server query {0} is not supported, should be transport=udp or transport=tcp")]
+    IceServerTransportNotSupport(String),
+
     #[error("SecretKey parse error, {0}")]
     Libsecp256k1SecretKeyParse(String),
 
@@ -132,6 +135,9 @@ pub enum Error {
     #[error("failed to close previous when registering, {0}")]
     SwarmToClosePrevTransport(String),
 
+    #[error("Swarm reached max connections: {0}")]
+    SwarmConnectionsFull(usize),
+
     #[error("call lock() failed")]
     SessionTryLockFailed,
 
@@ -0,0 +1,96 @@
+//! Typed events of Swarm and DHT for applications.
+//! Subscribers of [EventBus] receive a `Stream` of [RingEvent], which can be consumed
+//! both natively and in browser.
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::channel::mpsc;
+use serde::Deserialize;
+use serde::Serialize;
+use web3::types::Address;
+
+use crate::dht::Did;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum RingEvent {
+    /// A transport with peer is connected and registered
+    PeerConnected(Address),
+    /// A transport with peer is removed from swarm
+    PeerDisconnected(Address),
+    /// Successor list of local dht changed
+    SuccessorChanged(Vec<Did>),
+    /// Predecessor of local dht changed
+    PredecessorChanged(Option<Did>),
+    /// A vnode is stored on local dht
+    VNodeStored(Did),
+    /// A vnode is fetched into local cache
+    VNodeFetched(Did),
+    /// A member joined a subring which is stored on local dht
+    SubRingJoined { subring: Did, member: Did },
+    /// A custom message sent to local node is received and decrypted
+    CustomMessage { origin: Did, data: Vec<u8> },
+    /// A TURN credential is issued to local node by peer of the address
+    TurnCredentialReceived(Address),
+}
+
+pub type EventReceiver = mpsc::UnboundedReceiver<RingEvent>;
+
+/// Broadcast [RingEvent] to all subscribers.
+/// Subscribers which dropped their receivers are removed on next publishing.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<RingEvent>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe events, the receiver implements `Stream<Item = RingEvent>`.
+    pub fn subscribe(&self) -> EventReceiver {
+        let (tx, rx) = mpsc::unbounded();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    pub fn publish(&self, event: RingEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+        }
+    }
+
+    pub fn subscribers_count(&self) -> usize {
+        self.subscribers.lock().map(|s| s.len()).unwrap_or(0)
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::ecc::SecretKey;
+
+    #[test]
+    fn test_publish_to_all_subscribers() {
+        let bus = EventBus::new();
+        let mut rx1 = bus.subscribe();
+        let rx2 = bus.subscribe();
+        let addr = SecretKey::random().address();
+
+        bus.publish(RingEvent::PeerConnected(addr));
+        drop(rx2);
+        bus.publish(RingEvent::PeerDisconnected(addr));
+        assert_eq!(bus.subscribers_count(), 1);
+
+        futures::executor::block_on(async {
+            assert_eq!(rx1.next().await, Some(RingEvent::PeerConnected(addr)));
+            assert_eq!(rx1.next().await, Some(RingEvent::PeerDisconnected(addr)));
+        });
+    }
+}
@@ -67,13 +67,16 @@ pub mod channels;
 pub mod dht;
 pub mod ecc;
 pub mod err;
+pub mod event;
 pub mod macros;
 pub mod message;
+pub mod metrics;
 pub mod prelude;
 pub mod session;
 pub mod storage;
 pub mod swarm;
 pub mod transports;
+pub mod turn;
 pub mod types;
 pub mod utils;
 
@@ -9,6 +9,7 @@ use crate::dht::PeerRingRemoteAction;
 use crate::err::Error;
 use crate::err::Result;
 use crate::message::types::AlreadyConnected;
+use crate::message::types::ConnectNodeRefused;
 use crate::message::types::ConnectNodeReport;
 use crate::message::types::ConnectNodeSend;
 use crate::message::types::FindSuccessorReport;
@@ -23,14 +24,25 @@ use crate::message::MessagePayload;
 use crate::message::PayloadSender;
 use crate::prelude::RTCSdpType;
 use crate::swarm::TransportManager;
+use crate::types::ice_transport::IceTransport;
 use crate::types::ice_transport::IceTrickleScheme;
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
 #[cfg_attr(not(feature = "wasm"), async_trait)]
 impl HandleMsg<LeaveDHT> for MessageHandler {
-    async fn handle(&self, _ctx: &MessagePayload<Message>, msg: &LeaveDHT) -> Result<()> {
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &LeaveDHT) -> Result<()> {
+        // A node can only announce leave of itself, or be removed by local swarm.
+        if ctx.addr != self.swarm.address() && msg.id != ctx.addr.into() {
+            log::warn!("ignore LeaveDHT of {:?} sent by {:?}", msg.id, ctx.addr);
+            return Ok(());
+        }
         let mut dht = self.dht.lock().await;
+        let prev = Self::neighbours(&dht);
         dht.remove(msg.id);
+        if dht.predecessor == Some(msg.id) {
+            dht.predecessor = None;
+        }
+        self.publish_neighbours_change(prev, &dht);
         Ok(())
     }
 }
@@ -69,7 +81,7 @@ impl HandleMsg<JoinDHT> for MessageHandler {
 #[cfg_attr(not(feature = "wasm"), async_trait)]
 impl HandleMsg<ConnectNodeSend> for MessageHandler {
     async fn handle(&self, ctx: &MessagePayload<Message>, msg: &ConnectNodeSend) -> Result<()> {
-        let dht = self.dht.lock().await;
+        let mut dht = self.dht.lock().await;
         let mut relay = ctx.relay.clone();
 
         if dht.id != relay.destination {
@@ -91,6 +103,17 @@ impl HandleMsg<ConnectNodeSend> for MessageHandler {
         relay.relay(dht.id, None)?;
         match self.swarm.get_transport(&relay.sender()) {
             None => {
+                if let Err(e) = self.reserve_connection(&mut dht).await {
+                    return self
+                        .send_report_message(
+                            Message::ConnectNodeRefused(ConnectNodeRefused {
+                                transport_uuid: msg.transport_uuid.clone(),
+                                reason: e.to_string(),
+                            }),
+                            relay,
+                        )
+                        .await;
+                }
                 let trans = self.swarm.new_transport().await?;
                 let sender_id = relay.sender();
                 trans
@@ -147,6 +170,29 @@ impl HandleMsg<ConnectNodeReport> for MessageHandler {
     }
 }
 
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<ConnectNodeRefused> for MessageHandler {
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &ConnectNodeRefused) -> Result<()> {
+        let dht = self.dht.lock().await;
+        let mut relay = ctx.relay.clone();
+
+        relay.relay(dht.id, None)?;
+        if relay.next_hop.is_some() {
+            self.transpond_payload(ctx, relay).await
+        } else {
+            let uuid = uuid::Uuid::from_str(&msg.transport_uuid)
+                .map_err(|_| Error::InvalidTransportUuid)?;
+            if let Some(transport) = self.swarm.find_pending_transport(uuid)? {
+                self.swarm.pop_pending_transport(transport.id)?;
+                transport.close().await?;
+            }
+            log::warn!("connection to {:?} refused: {}", relay.sender(), msg.reason);
+            Ok(())
+        }
+    }
+}
+
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
 #[cfg_attr(not(feature = "wasm"), async_trait)]
 impl HandleMsg<AlreadyConnected> for MessageHandler {
@@ -657,6 +703,42 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_leave_dht() -> Result<()> {
+        let key1 = SecretKey::random();
+        let key2 = SecretKey::random();
+        let (did1, dht1, swarm1, node1) = prepare_node(&key1);
+        let (did2, dht2, swarm2, node2) = prepare_node(&key2);
+        test_only_two_nodes_establish_connection(
+            (&key1, dht1, &swarm1, &node1),
+            (&key2, dht2.clone(), &swarm2, &node2),
+        )
+        .await?;
+        assert!(dht2.lock().await.successor.list().contains(&did1));
+
+        // node1 cannot announce leave of another node
+        let did3: Did = SecretKey::random().address().into();
+        dht2.lock().await.join(did3);
+        let fingers = dht2.lock().await.finger.list().clone();
+        node1
+            .send_direct_message(Message::LeaveDHT(LeaveDHT { id: did3 }), did2)
+            .await?;
+        let ev = node2.listen_once().await.unwrap();
+        assert!(matches!(ev.data, Message::LeaveDHT(LeaveDHT{id}) if id == did3));
+        assert_eq!(dht2.lock().await.finger.list(), &fingers);
+
+        node1.leave().await?;
+        let ev = node2.listen_once().await.unwrap();
+        assert_eq!(ev.addr, key1.address());
+        assert!(matches!(ev.data, Message::LeaveDHT(LeaveDHT{id}) if id == did1));
+        assert!(!dht2.lock().await.successor.list().contains(&did1));
+        assert!(!dht2.lock().await.finger.contains(&Some(did1)));
+
+        swarm1.close_all_transports().await?;
+        assert!(swarm1.get_transports().is_empty());
+        Ok(())
+    }
+
     fn gen_triple_ordered_keys() -> (SecretKey, SecretKey, SecretKey) {
         let mut keys = Vec::from_iter(std::iter::repeat_with(SecretKey::random).take(3));
         keys.sort_by(|a, b| {
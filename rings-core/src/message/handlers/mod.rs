@@ -3,34 +3,51 @@ use std::sync::Arc;
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use futures::lock::Mutex;
+use tracing::Instrument;
 use web3::types::Address;
 
 use super::CustomMessage;
 use super::MaybeEncrypted;
 use super::Message;
 use super::MessagePayload;
+use super::MessageRelay;
 use super::OriginVerificationGen;
 use super::PayloadSender;
+use super::PingReport;
+use super::RoutingTableReport;
 use crate::dht::Chord;
+use crate::dht::Did;
 use crate::dht::PeerRing;
 use crate::dht::PeerRingAction;
 use crate::err::Error;
 use crate::err::Result;
+use crate::event::EventReceiver;
+use crate::event::RingEvent;
 use crate::prelude::RTCSdpType;
 use crate::prelude::Transport;
 use crate::session::SessionManager;
+use crate::storage::MemStorage;
 use crate::swarm::Swarm;
 use crate::swarm::TransportManager;
+use crate::turn::TurnCredential;
+use crate::turn::TurnCredentialIssuer;
+use crate::types::ice_transport::IceTransport;
 use crate::types::ice_transport::IceTrickleScheme;
 
 /// Operator and Handler for Connection
 pub mod connection;
+/// Operator and Handler for ping diagnostics
+pub mod ping;
+/// Operator and Handler for routing tables of remote nodes
+pub mod routing;
 /// Operator and handler for DHT stablization
 pub mod stablization;
 /// Operator and Handler for Storage
 pub mod storage;
 /// Operator and Handler for SubRing
 pub mod subring;
+/// Operator and Handler for TURN credentials
+pub mod turn;
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
 #[cfg_attr(not(feature = "wasm"), async_trait)]
@@ -55,6 +72,11 @@ pub struct MessageHandler {
     dht: Arc<Mutex<PeerRing>>,
     swarm: Arc<Swarm>,
     callback: Arc<Mutex<Option<CallbackFn>>>,
+    turn_issuer: Arc<Mutex<Option<TurnCredentialIssuer>>>,
+    turn_credentials: Arc<MemStorage<Address, TurnCredential>>,
+    ping_reports: Arc<MemStorage<u128, PingReport>>,
+    /// reports of routing table, with epoch time in ms when they are received
+    routing_tables: Arc<MemStorage<u128, (u128, RoutingTableReport)>>,
 }
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
@@ -73,6 +95,10 @@ impl MessageHandler {
             dht,
             swarm,
             callback: Arc::new(Mutex::new(Some(callback))),
+            turn_issuer: Arc::new(Mutex::new(None)),
+            turn_credentials: Arc::new(MemStorage::new()),
+            ping_reports: Arc::new(MemStorage::new()),
+            routing_tables: Arc::new(MemStorage::new()),
         }
     }
 
@@ -81,6 +107,10 @@ impl MessageHandler {
             dht,
             swarm,
             callback: Arc::new(Mutex::new(None)),
+            turn_issuer: Arc::new(Mutex::new(None)),
+            turn_credentials: Arc::new(MemStorage::new()),
+            ping_reports: Arc::new(MemStorage::new()),
+            routing_tables: Arc::new(MemStorage::new()),
         }
     }
 
@@ -89,17 +119,92 @@ impl MessageHandler {
         *cb = Some(f)
     }
 
+    /// Subscribe events of swarm and dht.
+    pub fn subscribe(&self) -> EventReceiver {
+        self.swarm.subscribe()
+    }
+
+    /// Get a copy of local dht, storage and cache of the copy are shared with origin.
+    pub async fn dht_snapshot(&self) -> PeerRing {
+        self.dht.lock().await.clone()
+    }
+
+    pub(crate) fn publish(&self, event: RingEvent) {
+        self.swarm.event_bus().publish(event)
+    }
+
+    fn neighbours(dht: &PeerRing) -> (Vec<Did>, Option<Did>) {
+        (dht.successor.list(), dht.predecessor)
+    }
+
+    /// Publish SuccessorChanged and PredecessorChanged if neighbours differ from `prev`.
+    fn publish_neighbours_change(&self, prev: (Vec<Did>, Option<Did>), dht: &PeerRing) {
+        let (successors, predecessor) = Self::neighbours(dht);
+        if prev.0 != successors {
+            self.publish(RingEvent::SuccessorChanged(successors));
+        }
+        if prev.1 != predecessor {
+            self.publish(RingEvent::PredecessorChanged(predecessor));
+        }
+    }
+
     // disconnect a node if a node is in DHT
     pub async fn disconnect(&self, address: Address) {
         let mut dht = self.dht.lock().await;
+        let prev = Self::neighbours(&dht);
         dht.remove(address.into());
+        self.publish_neighbours_change(prev, &dht);
         self.swarm.remove_transport(&address);
     }
 
+    /// Pick a transport to be evicted when swarm is full.
+    /// Successors and predecessor are never evicted, peers not in finger table
+    /// are preferred to finger entries, and the most idle one is chosen among them.
+    pub fn eviction_candidate(&self, dht: &PeerRing) -> Option<Address> {
+        self.swarm
+            .get_addresses()
+            .into_iter()
+            .filter(|addr| !dht.is_neighbour((*addr).into()))
+            .min_by_key(|addr| {
+                (
+                    dht.is_finger((*addr).into()),
+                    self.swarm.last_active(addr).unwrap_or(0),
+                )
+            })
+    }
+
+    /// Make room for a new transport, evicting one if swarm is full.
+    pub async fn reserve_connection(&self, dht: &mut PeerRing) -> Result<()> {
+        if !self.swarm.is_full() {
+            return Ok(());
+        }
+        let max = self.swarm.max_connections().unwrap_or_default();
+        let address = self
+            .eviction_candidate(dht)
+            .ok_or(Error::SwarmConnectionsFull(max))?;
+        log::info!("swarm is full, evict transport of {:?}", address);
+        if let Some((_, trans)) = self.swarm.remove_transport(&address) {
+            if let Err(e) = trans.close().await {
+                log::warn!("failed to close evicted transport: {}", e);
+            }
+        }
+        let prev = Self::neighbours(dht);
+        dht.remove(address.into());
+        self.publish_neighbours_change(prev, dht);
+        Ok(())
+    }
+
+    /// Same as `reserve_connection`, but lock dht by itself.
+    pub async fn ensure_capacity(&self) -> Result<()> {
+        let mut dht = self.dht.lock().await;
+        self.reserve_connection(&mut dht).await
+    }
+
     pub async fn connect(&self, address: &Address) -> Result<Arc<Transport>> {
         if let Some(t) = self.swarm.get_transport(address) {
             return Ok(t);
         }
+        self.ensure_capacity().await?;
 
         let target_id = address.to_owned().into();
         let transport = self.swarm.new_transport().await?;
@@ -127,6 +232,27 @@ impl MessageHandler {
         Ok(transport)
     }
 
+    /// Announce leave to successors and predecessor, before the node going offline.
+    pub async fn leave(&self) -> Result<()> {
+        let (id, mut neighbours) = {
+            let dht = self.dht.lock().await;
+            let (mut successors, predecessor) = Self::neighbours(&dht);
+            successors.extend(predecessor);
+            (dht.id, successors)
+        };
+        neighbours.sort();
+        neighbours.dedup();
+        for did in neighbours.into_iter().filter(|did| *did != id) {
+            if let Err(e) = self
+                .send_direct_message(Message::LeaveDHT(super::LeaveDHT { id }), did)
+                .await
+            {
+                log::warn!("failed to announce leave to {:?}: {}", did, e);
+            }
+        }
+        Ok(())
+    }
+
     async fn invoke_callback(&self, payload: &MessagePayload<Message>) -> Result<()> {
         let mut callback = self.callback.lock().await;
         if let Some(ref mut cb) = *callback {
@@ -145,15 +271,54 @@ impl MessageHandler {
         Ok(decrypt_msg)
     }
 
+    fn publish_custom_message(
+        &self,
+        payload: &MessagePayload<Message>,
+        msg: &MaybeEncrypted<CustomMessage>,
+    ) {
+        if payload.relay.destination != self.swarm.address().into() {
+            return;
+        }
+        match self.decrypt_msg(msg) {
+            Ok(CustomMessage(data)) => self.publish(RingEvent::CustomMessage {
+                origin: payload.relay.origin(),
+                data,
+            }),
+            Err(e) => log::warn!("failed to decrypt custom message: {}", e),
+        }
+    }
+
+    /// Handle a payload in a span carrying its `tx_id`, origin, destination, hop and message,
+    /// so logs of one payload can be traced across nodes.
     #[cfg_attr(feature = "wasm", async_recursion(?Send))]
     #[cfg_attr(not(feature = "wasm"), async_recursion)]
     pub async fn handle_payload(&self, payload: &MessagePayload<Message>) -> Result<()> {
-        match &payload.data {
+        let span = tracing::info_span!(
+            "payload",
+            node = ?*Did::from(self.swarm.address()),
+            tx_id = %payload.tx_id.inner(),
+            origin = ?*payload.relay.origin(),
+            destination = ?*payload.relay.destination,
+            hop = payload.relay.path.len(),
+            method = ?payload.relay.method,
+            message = payload.data.kind(),
+        );
+        self.dispatch_payload(payload).instrument(span).await
+    }
+
+    async fn dispatch_payload(&self, payload: &MessagePayload<Message>) -> Result<()> {
+        log::debug!("handle payload");
+        self.swarm
+            .metrics()
+            .inc_message_handled(payload.data.kind());
+        let prev = Self::neighbours(&*self.dht.lock().await);
+        let ret = match &payload.data {
             Message::JoinDHT(ref msg) => self.handle(payload, msg).await,
             Message::LeaveDHT(ref msg) => self.handle(payload, msg).await,
             Message::ConnectNodeSend(ref msg) => self.handle(payload, msg).await,
             Message::ConnectNodeReport(ref msg) => self.handle(payload, msg).await,
             Message::AlreadyConnected(ref msg) => self.handle(payload, msg).await,
+            Message::ConnectNodeRefused(ref msg) => self.handle(payload, msg).await,
             Message::FindSuccessorSend(ref msg) => self.handle(payload, msg).await,
             Message::FindSuccessorReport(ref msg) => self.handle(payload, msg).await,
             Message::NotifyPredecessorSend(ref msg) => self.handle(payload, msg).await,
@@ -161,6 +326,13 @@ impl MessageHandler {
             Message::SearchVNode(ref msg) => self.handle(payload, msg).await,
             Message::FoundVNode(ref msg) => self.handle(payload, msg).await,
             Message::StoreVNode(ref msg) => self.handle(payload, msg).await,
+            Message::JoinSubRing(ref msg) => self.handle(payload, msg).await,
+            Message::TurnCredentialSend(ref msg) => self.handle(payload, msg).await,
+            Message::TurnCredentialReport(ref msg) => self.handle(payload, msg).await,
+            Message::PingSend(ref msg) => self.handle(payload, msg).await,
+            Message::PingReport(ref msg) => self.handle(payload, msg).await,
+            Message::GetRoutingTable(ref msg) => self.handle(payload, msg).await,
+            Message::RoutingTableReport(ref msg) => self.handle(payload, msg).await,
             Message::MultiCall(ref msg) => {
                 for message in msg.messages.iter().cloned() {
                     let payload = MessagePayload::new(
@@ -173,12 +345,17 @@ impl MessageHandler {
                 }
                 Ok(())
             }
-            Message::CustomMessage(_) => Ok(()),
+            Message::CustomMessage(ref msg) => {
+                self.publish_custom_message(payload, msg);
+                Ok(())
+            }
             x => Err(Error::MessageHandlerUnsupportMessageType(format!(
                 "{:?}",
                 x
             ))),
-        }?;
+        };
+        self.publish_neighbours_change(prev, &*self.dht.lock().await);
+        ret?;
         if let Err(e) = self.invoke_callback(payload).await {
             log::warn!("invoke callback error: {}", e);
         }
@@ -191,6 +368,7 @@ impl MessageHandler {
     pub async fn listen_once(&self) -> Option<MessagePayload<Message>> {
         if let Some(payload) = self.swarm.poll_message().await {
             if !payload.verify() {
+                self.swarm.metrics().inc_verify_failures();
                 log::error!("Cannot verify msg or it's expired: {:?}", payload);
             }
             if let Err(e) = self.handle_payload(&payload).await {
@@ -217,6 +395,22 @@ impl PayloadSender<Message> for MessageHandler {
     ) -> Result<()> {
         self.swarm.do_send_payload(address, payload).await
     }
+
+    async fn transpond_payload(
+        &self,
+        payload: &MessagePayload<Message>,
+        relay: MessageRelay,
+    ) -> Result<()> {
+        self.swarm.metrics().inc_relay_transponds();
+        log::debug!("transpond payload to {:?}", relay.next_hop);
+        self.send_payload(MessagePayload::new(
+            payload.data.clone(),
+            self.swarm.session_manager(),
+            OriginVerificationGen::Stick(payload.origin_verification.clone()),
+            relay,
+        )?)
+        .await
+    }
 }
 
 #[cfg(not(feature = "wasm"))]
@@ -237,6 +431,7 @@ mod listener {
             pin_mut!(payloads);
             while let Some(payload) = payloads.next().await {
                 if !payload.verify() {
+                    self.swarm.metrics().inc_verify_failures();
                     log::error!("Cannot verify msg or it's expired: {:?}", payload);
                     continue;
                 }
@@ -281,6 +476,7 @@ pub mod test {
     use std::sync::Arc;
 
     use futures::lock::Mutex;
+    use futures::StreamExt;
     use tokio::time::sleep;
     use tokio::time::Duration;
     use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
@@ -347,6 +543,89 @@ pub mod test {
         Ok((handler1, handler2))
     }
 
+    #[tokio::test]
+    async fn test_evict_when_swarm_is_full() -> Result<()> {
+        let stun = "stun://stun.l.google.com:19302";
+        let key = SecretKey::random();
+        let sm = SessionManager::new_with_seckey(&key).unwrap();
+        let swarm = Arc::new(Swarm::new_with_max_connections(
+            stun,
+            key.address(),
+            sm,
+            Some(3),
+        ));
+        let dht = Arc::new(Mutex::new(PeerRing::new(key.address().into())));
+        let handler = MessageHandler::new(dht.clone(), swarm.clone());
+
+        let neighbour = SecretKey::random().address();
+        let finger = SecretKey::random().address();
+        let idle = SecretKey::random().address();
+        {
+            let mut dht = dht.lock().await;
+            dht.successor.update(neighbour.into());
+            dht.finger.set(0, &finger.into());
+        }
+        for addr in [neighbour, finger, idle] {
+            swarm.register(&addr, swarm.new_transport().await?).await?;
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(swarm.is_full());
+
+        // peer which is neither a neighbour nor a finger goes first
+        handler.ensure_capacity().await?;
+        assert!(swarm.get_transport(&idle).is_none());
+        assert!(!swarm.is_full());
+
+        // then the most idle finger
+        let new_finger = SecretKey::random().address();
+        dht.lock().await.finger.set(1, &new_finger.into());
+        swarm
+            .register(&new_finger, swarm.new_transport().await?)
+            .await?;
+        handler.ensure_capacity().await?;
+        assert!(swarm.get_transport(&finger).is_none());
+        assert!(!dht.lock().await.is_finger(finger.into()));
+        assert!(swarm.get_transport(&new_finger).is_some());
+        assert!(swarm.get_transport(&neighbour).is_some());
+
+        // neighbours are never evicted
+        let other = SecretKey::random().address();
+        {
+            let mut dht = dht.lock().await;
+            dht.successor.update(new_finger.into());
+            dht.predecessor = Some(other.into());
+        }
+        swarm.register(&other, swarm.new_transport().await?).await?;
+        assert!(matches!(
+            handler.ensure_capacity().await,
+            Err(Error::SwarmConnectionsFull(3))
+        ));
+        assert!(matches!(
+            handler.connect(&SecretKey::random().address()).await,
+            Err(Error::SwarmConnectionsFull(3))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_publish_events_on_disconnect() -> Result<()> {
+        let key1 = SecretKey::random();
+        let key2 = SecretKey::random();
+        let (handler1, _handler2) = create_connected_pair(key1, key2).await?;
+        let mut events = handler1.subscribe();
+
+        handler1.disconnect(key2.address()).await;
+        assert_eq!(
+            events.next().await,
+            Some(RingEvent::SuccessorChanged(vec![]))
+        );
+        assert_eq!(
+            events.next().await,
+            Some(RingEvent::PeerDisconnected(key2.address()))
+        );
+        Ok(())
+    }
+
     #[derive(Clone)]
     struct MessageCallbackInstance {
         #[allow(clippy::type_complexity)]
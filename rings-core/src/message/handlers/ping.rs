@@ -0,0 +1,166 @@
+#![warn(missing_docs)]
+use async_trait::async_trait;
+
+use crate::dht::Chord;
+use crate::dht::Did;
+use crate::dht::PeerRingAction;
+use crate::err::Error;
+use crate::err::Result;
+use crate::message::types::Message;
+use crate::message::types::PingHop;
+use crate::message::types::PingReport;
+use crate::message::types::PingSend;
+use crate::message::HandleMsg;
+use crate::message::MessageHandler;
+use crate::message::MessagePayload;
+use crate::message::OriginVerificationGen;
+use crate::message::PayloadSender;
+use crate::prelude::uuid;
+use crate::utils;
+
+/// Reports not taken are dropped after this long, since their ping was sent.
+const PING_REPORT_TTL_MS: u128 = 60 * 1000;
+
+/// PingOperator should imply routing a ping to a did, and collecting its hops
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait PingOperator {
+    /// Send a ping to node responsible for `target`, return id of the ping
+    async fn ping(&self, target: &Did) -> Result<u128>;
+    /// Take report of ping `id`, if it's received
+    fn take_ping_report(&self, id: u128) -> Option<PingReport>;
+}
+
+impl MessageHandler {
+    fn hop(&self) -> PingHop {
+        PingHop {
+            did: self.swarm.address().into(),
+            ts_ms: utils::get_epoch_ms(),
+        }
+    }
+
+    /// Next hop to `target` and whether it's the last one,
+    /// None if local node is responsible for `target`.
+    /// Also used by other messages routed like [PingSend].
+    pub(super) async fn next_hop_to(
+        &self,
+        target: Did,
+        last_hop: bool,
+    ) -> Result<Option<(Did, bool)>> {
+        let dht = self.dht.lock().await;
+        if last_hop || target == dht.id {
+            return Ok(None);
+        }
+        match dht.find_successor(target)? {
+            PeerRingAction::Some(id) if id == dht.id => Ok(None),
+            PeerRingAction::Some(id) => Ok(Some((id, true))),
+            PeerRingAction::RemoteAction(next, _) => Ok(Some((next, false))),
+            act => Err(Error::PeerRingUnexpectedAction(act)),
+        }
+    }
+
+    fn save_ping_report(&self, report: PingReport) {
+        let now = utils::get_epoch_ms();
+        for (id, r) in self.ping_reports.items() {
+            if r.hops[0].ts_ms + PING_REPORT_TTL_MS < now {
+                self.ping_reports.remove(&id);
+            }
+        }
+        let id = report.id;
+        self.ping_reports.set(&id, report);
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl PingOperator for MessageHandler {
+    async fn ping(&self, target: &Did) -> Result<u128> {
+        let id = uuid::Uuid::new_v4().as_u128();
+        let hops = vec![self.hop()];
+        match self.next_hop_to(*target, false).await? {
+            None => self.save_ping_report(PingReport {
+                id,
+                target: *target,
+                hops,
+            }),
+            Some((next, last_hop)) => {
+                self.send_message(
+                    Message::PingSend(PingSend {
+                        id,
+                        target: *target,
+                        last_hop,
+                        hops,
+                    }),
+                    next,
+                    next,
+                )
+                .await?
+            }
+        }
+        Ok(id)
+    }
+
+    fn take_ping_report(&self, id: u128) -> Option<PingReport> {
+        self.ping_reports.remove(&id).map(|(_, r)| r)
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<PingSend> for MessageHandler {
+    /// Record this hop, then report hops if local node is responsible for target,
+    /// or forward the ping, which is signed again since its hops are changed.
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &PingSend) -> Result<()> {
+        let mut hops = msg.hops.clone();
+        hops.push(self.hop());
+        let mut relay = ctx.relay.clone();
+        match self.next_hop_to(msg.target, msg.last_hop).await? {
+            None => {
+                relay.relay(self.swarm.address().into(), None)?;
+                self.send_report_message(
+                    Message::PingReport(PingReport {
+                        id: msg.id,
+                        target: msg.target,
+                        hops,
+                    }),
+                    relay,
+                )
+                .await
+            }
+            Some((next, last_hop)) => {
+                relay.relay(self.swarm.address().into(), Some(next))?;
+                relay.reset_destination(next)?;
+                self.send_payload(MessagePayload::new(
+                    Message::PingSend(PingSend {
+                        id: msg.id,
+                        target: msg.target,
+                        last_hop,
+                        hops,
+                    }),
+                    self.swarm.session_manager(),
+                    OriginVerificationGen::Origin,
+                    relay,
+                )?)
+                .await
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<PingReport> for MessageHandler {
+    /// Save report with the hop back to origin of ping.
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &PingReport) -> Result<()> {
+        let mut relay = ctx.relay.clone();
+        relay.relay(self.swarm.address().into(), None)?;
+        if relay.next_hop.is_some() {
+            self.transpond_payload(ctx, relay).await
+        } else {
+            let mut report = msg.clone();
+            report.hops.push(self.hop());
+            self.save_ping_report(report);
+            Ok(())
+        }
+    }
+}
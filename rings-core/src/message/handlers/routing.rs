@@ -0,0 +1,139 @@
+#![warn(missing_docs)]
+use async_trait::async_trait;
+
+use crate::dht::Did;
+use crate::dht::PeerRing;
+use crate::err::Result;
+use crate::message::types::GetRoutingTable;
+use crate::message::types::Message;
+use crate::message::types::RoutingTableReport;
+use crate::message::HandleMsg;
+use crate::message::MessageHandler;
+use crate::message::MessagePayload;
+use crate::message::OriginVerificationGen;
+use crate::message::PayloadSender;
+use crate::prelude::uuid;
+use crate::utils;
+
+/// Reports not taken are dropped after this long, since they are received.
+const ROUTING_TABLE_TTL_MS: u128 = 60 * 1000;
+
+/// RoutingTableOperator should imply asking routing table of node responsible for a did
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait RoutingTableOperator {
+    /// Ask routing table of node responsible for `target`, return id of the request
+    async fn request_routing_table(&self, target: &Did) -> Result<u128>;
+    /// Take routing table reported for request `id`, if it's received
+    fn take_routing_table(&self, id: u128) -> Option<RoutingTableReport>;
+}
+
+impl MessageHandler {
+    fn save_routing_table(&self, report: RoutingTableReport) {
+        let now = utils::get_epoch_ms();
+        for (id, (ts_ms, _)) in self.routing_tables.items() {
+            if ts_ms + ROUTING_TABLE_TTL_MS < now {
+                self.routing_tables.remove(&id);
+            }
+        }
+        let id = report.id;
+        self.routing_tables.set(&id, (now, report));
+    }
+}
+
+fn routing_table_report(id: u128, target: Did, dht: &PeerRing) -> RoutingTableReport {
+    let mut finger: Vec<Did> = vec![];
+    for did in dht.finger.list().iter().flatten() {
+        if !finger.contains(did) {
+            finger.push(*did);
+        }
+    }
+    RoutingTableReport {
+        id,
+        target,
+        did: dht.id,
+        predecessor: dht.predecessor,
+        successors: dht.successor.list(),
+        finger,
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl RoutingTableOperator for MessageHandler {
+    async fn request_routing_table(&self, target: &Did) -> Result<u128> {
+        let id = uuid::Uuid::new_v4().as_u128();
+        match self.next_hop_to(*target, false).await? {
+            None => {
+                let report = routing_table_report(id, *target, &*self.dht.lock().await);
+                self.save_routing_table(report)
+            }
+            Some((next, last_hop)) => {
+                self.send_message(
+                    Message::GetRoutingTable(GetRoutingTable {
+                        id,
+                        target: *target,
+                        last_hop,
+                    }),
+                    next,
+                    next,
+                )
+                .await?
+            }
+        }
+        Ok(id)
+    }
+
+    fn take_routing_table(&self, id: u128) -> Option<RoutingTableReport> {
+        self.routing_tables.remove(&id).map(|(_, (_, r))| r)
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<GetRoutingTable> for MessageHandler {
+    /// Report routing table if local node is responsible for target,
+    /// or forward the request, which is signed again since `last_hop` may be changed.
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &GetRoutingTable) -> Result<()> {
+        let mut relay = ctx.relay.clone();
+        match self.next_hop_to(msg.target, msg.last_hop).await? {
+            None => {
+                let report = routing_table_report(msg.id, msg.target, &*self.dht.lock().await);
+                relay.relay(self.swarm.address().into(), None)?;
+                self.send_report_message(Message::RoutingTableReport(report), relay)
+                    .await
+            }
+            Some((next, last_hop)) => {
+                relay.relay(self.swarm.address().into(), Some(next))?;
+                relay.reset_destination(next)?;
+                self.send_payload(MessagePayload::new(
+                    Message::GetRoutingTable(GetRoutingTable {
+                        id: msg.id,
+                        target: msg.target,
+                        last_hop,
+                    }),
+                    self.swarm.session_manager(),
+                    OriginVerificationGen::Origin,
+                    relay,
+                )?)
+                .await
+            }
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<RoutingTableReport> for MessageHandler {
+    /// Save report if local node sent the request.
+    async fn handle(&self, ctx: &MessagePayload<Message>, msg: &RoutingTableReport) -> Result<()> {
+        let mut relay = ctx.relay.clone();
+        relay.relay(self.swarm.address().into(), None)?;
+        if relay.next_hop.is_some() {
+            self.transpond_payload(ctx, relay).await
+        } else {
+            self.save_routing_table(msg.clone());
+            Ok(())
+        }
+    }
+}
@@ -7,6 +7,7 @@ use crate::dht::PeerRingAction;
 use crate::dht::PeerRingRemoteAction;
 use crate::err::Error;
 use crate::err::Result;
+use crate::event::RingEvent;
 use crate::message::types::FoundVNode;
 use crate::message::types::Message;
 use crate::message::types::SearchVNode;
@@ -46,12 +47,14 @@ impl TChordStorage for MessageHandler {
         match dht.lookup(id)? {
             PeerRingAction::SomeVNode(v) => {
                 dht.cache(v);
+                self.publish(RingEvent::VNodeFetched(*id));
                 Ok(())
             }
             PeerRingAction::None => Ok(()),
             PeerRingAction::RemoteAction(next, _) => {
                 self.send_direct_message(Message::SearchVNode(SearchVNode { id: *id }), next)
                     .await?;
+                self.swarm.metrics().lookup_started(*id);
                 Ok(())
             }
             act => Err(Error::PeerRingUnexpectedAction(act)),
@@ -61,8 +64,12 @@ impl TChordStorage for MessageHandler {
     /// Store VirtualNode, TryInto<VirtualNode> is implementated for alot of types
     async fn store(&self, vnode: VirtualNode) -> Result<()> {
         let dht = self.dht.lock().await;
+        let vid = vnode.did();
         match dht.store(vnode)? {
-            PeerRingAction::None => Ok(()),
+            PeerRingAction::None => {
+                self.publish(RingEvent::VNodeStored(vid));
+                Ok(())
+            }
             PeerRingAction::RemoteAction(target, PeerRingRemoteAction::FindAndStore(vnode)) => {
                 self.send_direct_message(
                     Message::StoreVNode(StoreVNode { data: vec![vnode] }),
@@ -120,7 +127,10 @@ impl HandleMsg<FoundVNode> for MessageHandler {
         } else {
             // When query successor, store in local cache
             for datum in msg.data.iter().cloned() {
+                let vid = datum.did();
                 dht.cache(datum);
+                self.swarm.metrics().lookup_finished(&vid);
+                self.publish(RingEvent::VNodeFetched(vid));
             }
             Ok(())
         }
@@ -135,9 +145,13 @@ impl HandleMsg<StoreVNode> for MessageHandler {
 
         let virtual_peer = msg.data.clone();
         for p in virtual_peer {
+            let vid = p.did();
             match dht.store(p) {
                 Ok(action) => match action {
-                    PeerRingAction::None => Ok(()),
+                    PeerRingAction::None => {
+                        self.publish(RingEvent::VNodeStored(vid));
+                        Ok(())
+                    }
                     PeerRingAction::RemoteAction(next, _) => {
                         let mut relay = ctx.relay.clone();
                         relay.reset_destination(next)?;
@@ -166,8 +180,12 @@ impl HandleMsg<SyncVNodeWithSuccessor> for MessageHandler {
 
         for data in msg.data.iter().cloned() {
             // only simply store here
+            let vid = data.did();
             match dht.store(data) {
-                Ok(PeerRingAction::None) => Ok(()),
+                Ok(PeerRingAction::None) => {
+                    self.publish(RingEvent::VNodeStored(vid));
+                    Ok(())
+                }
                 Ok(PeerRingAction::RemoteAction(
                     next,
                     PeerRingRemoteAction::FindAndStore(peer),
@@ -13,6 +13,7 @@ use crate::dht::SubRingManager;
 use crate::ecc::HashStr;
 use crate::err::Error;
 use crate::err::Result;
+use crate::event::RingEvent;
 use crate::message::types::JoinSubRing;
 use crate::message::types::Message;
 use crate::message::HandleMsg;
@@ -30,16 +31,21 @@ pub trait SubRingOperator {
     async fn create(&self, name: &str) -> Result<()>;
     /// join a subring
     async fn join(&self, name: &str) -> Result<()>;
+    /// list subrings stored on local dht
+    async fn list(&self) -> Vec<SubRing>;
 }
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
 #[cfg_attr(not(feature = "wasm"), async_trait)]
 impl SubRingOperator for MessageHandler {
     async fn create(&self, name: &str) -> Result<()> {
-        let dht = self.dht.lock().await;
-        let subring: SubRing = SubRing::new(name, &dht.id)?;
-        let vnode: VirtualNode = subring.clone().try_into()?;
-        dht.store_subring(&subring.clone())?;
+        let vnode: VirtualNode = {
+            let dht = self.dht.lock().await;
+            let subring: SubRing = SubRing::new(name, &dht.id)?;
+            dht.store_subring(&subring)?;
+            subring.try_into()?
+        };
+        // release dht lock here, `store` will acquire it again.
         self.store(vnode).await
     }
 
@@ -52,11 +58,22 @@ impl SubRingOperator for MessageHandler {
                 self.send_direct_message(Message::JoinSubRing(JoinSubRing { did: rid }), next)
                     .await
             }
-            Ok(PeerRingAction::None) => Ok(()),
+            Ok(PeerRingAction::None) => {
+                self.publish(RingEvent::SubRingJoined {
+                    subring: did,
+                    member: dht.id,
+                });
+                Ok(())
+            }
             Ok(act) => Err(Error::PeerRingUnexpectedAction(act)),
             Err(e) => Err(e),
         }
     }
+
+    async fn list(&self) -> Vec<SubRing> {
+        let dht = self.dht.lock().await;
+        dht.list_subrings()
+    }
 }
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
@@ -72,7 +89,13 @@ impl HandleMsg<JoinSubRing> for MessageHandler {
                 relay.reset_destination(next)?;
                 self.transpond_payload(ctx, relay).await
             }
-            Ok(PeerRingAction::None) => Ok(()),
+            Ok(PeerRingAction::None) => {
+                self.publish(RingEvent::SubRingJoined {
+                    subring: msg.did,
+                    member: origin,
+                });
+                Ok(())
+            }
             Ok(act) => Err(Error::PeerRingUnexpectedAction(act)),
             Err(e) => Err(e),
         }
@@ -0,0 +1,96 @@
+#![warn(missing_docs)]
+use async_trait::async_trait;
+use web3::types::Address;
+
+use crate::err::Error;
+use crate::err::Result;
+use crate::event::RingEvent;
+use crate::message::types::Message;
+use crate::message::types::TurnCredentialReport;
+use crate::message::types::TurnCredentialSend;
+use crate::message::HandleMsg;
+use crate::message::MessageHandler;
+use crate::message::MessagePayload;
+use crate::message::PayloadSender;
+use crate::turn::TurnCredential;
+use crate::turn::TurnCredentialIssuer;
+
+/// TurnCredentialOperator should imply issuing and requesting of TURN credentials
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait TurnCredentialOperator {
+    /// Issue credentials of local TURN server to peers which request them
+    async fn set_turn_issuer(&self, issuer: TurnCredentialIssuer);
+    /// Request a credential from connected peer of `issuer`
+    async fn request_turn_credential(&self, issuer: &Address) -> Result<()>;
+    /// Credential received from `issuer`, if it's not expired
+    fn turn_credential(&self, issuer: &Address) -> Option<TurnCredential>;
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl TurnCredentialOperator for MessageHandler {
+    async fn set_turn_issuer(&self, issuer: TurnCredentialIssuer) {
+        let mut turn_issuer = self.turn_issuer.lock().await;
+        *turn_issuer = Some(issuer);
+    }
+
+    async fn request_turn_credential(&self, issuer: &Address) -> Result<()> {
+        self.send_direct_message(
+            Message::TurnCredentialSend(TurnCredentialSend),
+            issuer.to_owned().into(),
+        )
+        .await
+    }
+
+    fn turn_credential(&self, issuer: &Address) -> Option<TurnCredential> {
+        self.turn_credentials
+            .get(issuer)
+            .filter(|credential| !credential.is_expired())
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<TurnCredentialSend> for MessageHandler {
+    /// Issue a credential to origin of message, whose session is verified.
+    async fn handle(&self, ctx: &MessagePayload<Message>, _msg: &TurnCredentialSend) -> Result<()> {
+        if !ctx.verify() {
+            return Err(Error::VerifySignatureFailed);
+        }
+        let credential = match self.turn_issuer.lock().await.as_ref() {
+            Some(issuer) => issuer.issue(&ctx.addr),
+            None => {
+                log::debug!("no TURN server to issue credential to {:?}", ctx.addr);
+                return Ok(());
+            }
+        };
+        let mut relay = ctx.relay.clone();
+        relay.relay(self.swarm.address().into(), None)?;
+        self.send_report_message(
+            Message::TurnCredentialReport(TurnCredentialReport { credential }),
+            relay,
+        )
+        .await
+    }
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+impl HandleMsg<TurnCredentialReport> for MessageHandler {
+    async fn handle(
+        &self,
+        ctx: &MessagePayload<Message>,
+        msg: &TurnCredentialReport,
+    ) -> Result<()> {
+        let mut relay = ctx.relay.clone();
+        relay.relay(self.swarm.address().into(), None)?;
+        if relay.next_hop.is_some() {
+            self.transpond_payload(ctx, relay).await
+        } else {
+            self.turn_credentials.set(&ctx.addr, msg.credential.clone());
+            self.publish(RingEvent::TurnCredentialReceived(ctx.addr));
+            Ok(())
+        }
+    }
+}
@@ -14,6 +14,11 @@ mod types;
 pub use types::*;
 
 mod handlers;
+pub use handlers::ping::PingOperator;
+pub use handlers::routing::RoutingTableOperator;
+pub use handlers::storage::TChordStorage;
+pub use handlers::subring::SubRingOperator;
+pub use handlers::turn::TurnCredentialOperator;
 pub use handlers::HandleMsg;
 pub use handlers::MessageCallback;
 pub use handlers::MessageHandler;
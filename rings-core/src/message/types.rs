@@ -9,6 +9,7 @@ use crate::ecc::PublicKey;
 use crate::ecc::SecretKey;
 use crate::err::Error;
 use crate::err::Result;
+use crate::turn::TurnCredential;
 
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
 pub struct ConnectNodeSend {
@@ -25,6 +26,12 @@ pub struct ConnectNodeReport {
     pub handshake_info: String,
 }
 
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
+pub struct ConnectNodeRefused {
+    pub transport_uuid: String,
+    pub reason: String,
+}
+
 #[derive(Debug, PartialEq, Eq, Deserialize, Serialize, Clone)]
 pub struct FindSuccessorSend {
     pub id: Did,
@@ -87,6 +94,62 @@ pub struct JoinSubRing {
     pub did: Did,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TurnCredentialSend;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TurnCredentialReport {
+    pub credential: TurnCredential,
+}
+
+/// A node passed by [PingSend], and epoch time in ms when it's received there.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PingHop {
+    pub did: Did,
+    pub ts_ms: u128,
+}
+
+/// Routed to node responsible for `target` by `find_successor`, recording each hop.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PingSend {
+    /// id to match the report
+    pub id: u128,
+    pub target: Did,
+    /// receiver is the successor of `target`
+    pub last_hop: bool,
+    pub hops: Vec<PingHop>,
+}
+
+/// Hops of [PingSend], reported by the node responsible for `target`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PingReport {
+    pub id: u128,
+    pub target: Did,
+    pub hops: Vec<PingHop>,
+}
+
+/// Routed to node responsible for `target` like [PingSend], asking for its routing table.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GetRoutingTable {
+    /// id to match the report
+    pub id: u128,
+    pub target: Did,
+    /// receiver is the successor of `target`
+    pub last_hop: bool,
+}
+
+/// Routing table of node `did`, which is responsible for `target` of [GetRoutingTable].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RoutingTableReport {
+    pub id: u128,
+    pub target: Did,
+    pub did: Did,
+    pub predecessor: Option<Did>,
+    pub successors: Vec<Did>,
+    /// distinct dids of finger table, in order of it
+    pub finger: Vec<Did>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct CustomMessage(pub Vec<u8>);
 
@@ -104,6 +167,7 @@ pub enum Message {
     ConnectNodeSend(ConnectNodeSend),
     AlreadyConnected(AlreadyConnected),
     ConnectNodeReport(ConnectNodeReport),
+    ConnectNodeRefused(ConnectNodeRefused),
     FindSuccessorSend(FindSuccessorSend),
     FindSuccessorReport(FindSuccessorReport),
     NotifyPredecessorSend(NotifyPredecessorSend),
@@ -113,6 +177,12 @@ pub enum Message {
     StoreVNode(StoreVNode),
     SyncVNodeWithSuccessor(SyncVNodeWithSuccessor),
     JoinSubRing(JoinSubRing),
+    TurnCredentialSend(TurnCredentialSend),
+    TurnCredentialReport(TurnCredentialReport),
+    PingSend(PingSend),
+    PingReport(PingReport),
+    GetRoutingTable(GetRoutingTable),
+    RoutingTableReport(RoutingTableReport),
     CustomMessage(MaybeEncrypted<CustomMessage>),
 }
 
@@ -123,6 +193,35 @@ impl std::fmt::Display for Message {
 }
 
 impl Message {
+    /// Name of message variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::MultiCall(_) => "MultiCall",
+            Message::JoinDHT(_) => "JoinDHT",
+            Message::LeaveDHT(_) => "LeaveDHT",
+            Message::ConnectNodeSend(_) => "ConnectNodeSend",
+            Message::AlreadyConnected(_) => "AlreadyConnected",
+            Message::ConnectNodeReport(_) => "ConnectNodeReport",
+            Message::ConnectNodeRefused(_) => "ConnectNodeRefused",
+            Message::FindSuccessorSend(_) => "FindSuccessorSend",
+            Message::FindSuccessorReport(_) => "FindSuccessorReport",
+            Message::NotifyPredecessorSend(_) => "NotifyPredecessorSend",
+            Message::NotifyPredecessorReport(_) => "NotifyPredecessorReport",
+            Message::SearchVNode(_) => "SearchVNode",
+            Message::FoundVNode(_) => "FoundVNode",
+            Message::StoreVNode(_) => "StoreVNode",
+            Message::SyncVNodeWithSuccessor(_) => "SyncVNodeWithSuccessor",
+            Message::JoinSubRing(_) => "JoinSubRing",
+            Message::TurnCredentialSend(_) => "TurnCredentialSend",
+            Message::TurnCredentialReport(_) => "TurnCredentialReport",
+            Message::PingSend(_) => "PingSend",
+            Message::PingReport(_) => "PingReport",
+            Message::GetRoutingTable(_) => "GetRoutingTable",
+            Message::RoutingTableReport(_) => "RoutingTableReport",
+            Message::CustomMessage(_) => "CustomMessage",
+        }
+    }
+
     pub fn custom(msg: &[u8], pubkey: &Option<PublicKey>) -> Result<Message> {
         let data = CustomMessage(msg.to_vec());
         let msg = MaybeEncrypted::new(data, pubkey)?;
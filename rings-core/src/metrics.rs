@@ -0,0 +1,164 @@
+//! Counters of Swarm, MessageHandler and Stabilization.
+//! Gauges like transports by state or size of storage can be read from their owners directly,
+//! so only values which can not be recovered later are collected here.
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use dashmap::DashMap;
+
+use crate::dht::Did;
+use crate::utils;
+
+/// Upper bounds (ms) of lookup latency histogram buckets.
+pub const LOOKUP_LATENCY_BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Lookups not answered within this time are dropped from pending list.
+const LOOKUP_TIMEOUT_MS: u128 = 60 * 1000;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    messages_handled: DashMap<&'static str, u64>,
+    relay_transponds: AtomicU64,
+    verify_failures: AtomicU64,
+    encode_errors: AtomicU64,
+    decode_errors: AtomicU64,
+    stabilization_rounds: AtomicU64,
+    /// start time (epoch ms) of lookups waiting for FoundVNode
+    pending_lookups: DashMap<Did, u128>,
+    lookup_latency_buckets: [AtomicU64; LOOKUP_LATENCY_BUCKETS_MS.len()],
+    lookup_latency_sum_ms: AtomicU64,
+    lookup_latency_count: AtomicU64,
+}
+
+/// A copy of all values in [Metrics].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub messages_handled: Vec<(&'static str, u64)>,
+    pub relay_transponds: u64,
+    pub verify_failures: u64,
+    pub encode_errors: u64,
+    pub decode_errors: u64,
+    pub stabilization_rounds: u64,
+    /// cumulative count of each bucket in [LOOKUP_LATENCY_BUCKETS_MS]
+    pub lookup_latency_buckets: Vec<(u64, u64)>,
+    pub lookup_latency_sum_ms: u64,
+    pub lookup_latency_count: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inc_message_handled(&self, kind: &'static str) {
+        *self.messages_handled.entry(kind).or_insert(0) += 1;
+    }
+
+    pub fn inc_relay_transponds(&self) {
+        self.relay_transponds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_verify_failures(&self) {
+        self.verify_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_encode_errors(&self) {
+        self.encode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_decode_errors(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_stabilization_rounds(&self) {
+        self.stabilization_rounds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a remote lookup of `id` is sent.
+    pub fn lookup_started(&self, id: Did) {
+        let now = utils::get_epoch_ms();
+        self.pending_lookups
+            .retain(|_, start| now.saturating_sub(*start) < LOOKUP_TIMEOUT_MS);
+        self.pending_lookups.insert(id, now);
+    }
+
+    /// Record that the result of lookup `id` arrived, observe its latency if it's pending.
+    pub fn lookup_finished(&self, id: &Did) {
+        if let Some((_, start)) = self.pending_lookups.remove(id) {
+            let elapsed = (utils::get_epoch_ms().saturating_sub(start)) as u64;
+            self.observe_lookup_latency(elapsed);
+        }
+    }
+
+    fn observe_lookup_latency(&self, ms: u64) {
+        if let Some(i) = LOOKUP_LATENCY_BUCKETS_MS.iter().position(|b| ms <= *b) {
+            self.lookup_latency_buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.lookup_latency_sum_ms.fetch_add(ms, Ordering::Relaxed);
+        self.lookup_latency_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut messages_handled = self
+            .messages_handled
+            .iter()
+            .map(|kv| (*kv.key(), *kv.value()))
+            .collect::<Vec<_>>();
+        messages_handled.sort();
+        let mut acc = 0;
+        let lookup_latency_buckets = LOOKUP_LATENCY_BUCKETS_MS
+            .iter()
+            .zip(self.lookup_latency_buckets.iter())
+            .map(|(le, count)| {
+                acc += count.load(Ordering::Relaxed);
+                (*le, acc)
+            })
+            .collect();
+        MetricsSnapshot {
+            messages_handled,
+            relay_transponds: self.relay_transponds.load(Ordering::Relaxed),
+            verify_failures: self.verify_failures.load(Ordering::Relaxed),
+            encode_errors: self.encode_errors.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            stabilization_rounds: self.stabilization_rounds.load(Ordering::Relaxed),
+            lookup_latency_buckets,
+            lookup_latency_sum_ms: self.lookup_latency_sum_ms.load(Ordering::Relaxed),
+            lookup_latency_count: self.lookup_latency_count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecc::SecretKey;
+
+    #[test]
+    fn test_metrics_snapshot() {
+        let metrics = Metrics::new();
+        metrics.inc_message_handled("JoinDHT");
+        metrics.inc_message_handled("JoinDHT");
+        metrics.inc_message_handled("FoundVNode");
+        metrics.inc_relay_transponds();
+        metrics.inc_decode_errors();
+
+        let id: Did = SecretKey::random().address().into();
+        metrics.lookup_finished(&id);
+        metrics.lookup_started(id);
+        metrics.lookup_finished(&id);
+        metrics.lookup_finished(&id);
+        metrics.observe_lookup_latency(300);
+        metrics.observe_lookup_latency(20000);
+
+        let s = metrics.snapshot();
+        assert_eq!(s.messages_handled, vec![("FoundVNode", 1), ("JoinDHT", 2)]);
+        assert_eq!(s.relay_transponds, 1);
+        assert_eq!(s.decode_errors, 1);
+        assert_eq!(s.encode_errors, 0);
+        assert_eq!(s.lookup_latency_count, 3);
+        assert_eq!(s.lookup_latency_buckets[0], (50, 1));
+        assert_eq!(s.lookup_latency_buckets[2], (250, 1));
+        assert_eq!(s.lookup_latency_buckets[3], (500, 2));
+        assert_eq!(s.lookup_latency_buckets[7], (10000, 2));
+    }
+}
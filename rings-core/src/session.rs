@@ -82,12 +82,18 @@ impl Session {
         }
     }
 
+    /// Epoch time in ms when session expires, `None` if it never expires.
+    pub fn expires_at_ms(&self) -> Option<u128> {
+        match self.auth.ttl_ms {
+            Ttl::Some(ttl_ms) => Some(self.auth.ts_ms + ttl_ms as u128),
+            Ttl::Never => None,
+        }
+    }
+
     pub fn is_expired(&self) -> bool {
-        if let Ttl::Some(ttl_ms) = self.auth.ttl_ms {
-            let now = utils::get_epoch_ms();
-            now > self.auth.ts_ms + ttl_ms as u128
-        } else {
-            false
+        match self.expires_at_ms() {
+            Some(ts) => utils::get_epoch_ms() > ts,
+            None => false,
         }
     }
 
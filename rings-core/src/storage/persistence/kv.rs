@@ -49,6 +49,11 @@ impl KvStorage {
     pub async fn new() -> Result<Self> {
         Self::new_with_cap(200000000).await
     }
+
+    /// Flush dirty buffers to disk, returns bytes flushed.
+    pub async fn flush(&self) -> Result<usize> {
+        self.db.flush_async().await.map_err(Error::SledError)
+    }
 }
 
 impl KvStorageBasic for KvStorage {
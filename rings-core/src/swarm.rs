@@ -14,12 +14,16 @@ use web3::types::Address;
 use crate::channels::Channel;
 use crate::err::Error;
 use crate::err::Result;
+use crate::event::EventBus;
+use crate::event::EventReceiver;
+use crate::event::RingEvent;
 use crate::message;
 use crate::message::Decoder;
 use crate::message::Encoder;
 use crate::message::Message;
 use crate::message::MessagePayload;
 use crate::message::PayloadSender;
+use crate::metrics::Metrics;
 use crate::session::SessionManager;
 use crate::storage::MemStorage;
 use crate::transports::Transport;
@@ -28,14 +32,24 @@ use crate::types::channel::Event;
 use crate::types::ice_transport::IceServer;
 use crate::types::ice_transport::IceTransport;
 use crate::types::ice_transport::IceTransportCallback;
+use crate::utils;
+
+/// Transport waiting for handshake, with its creation time (epoch ms).
+type PendingTransport = (Arc<Transport>, u128);
 
 pub struct Swarm {
     table: MemStorage<Address, Arc<Transport>>,
-    pending: Arc<Mutex<Vec<Arc<Transport>>>>,
+    /// last time (epoch ms) a message was sent to or received from an address
+    activity: MemStorage<Address, u128>,
+    /// max number of transports in table, `None` means unlimited
+    max_connections: Option<usize>,
+    pending: Arc<Mutex<Vec<PendingTransport>>>,
     ice_servers: Vec<IceServer>,
     transport_event_channel: Channel<Event>,
     session_manager: SessionManager,
     address: Address,
+    event_bus: EventBus,
+    metrics: Arc<Metrics>,
 }
 
 #[cfg_attr(feature = "wasm", async_trait(?Send))]
@@ -59,6 +73,16 @@ pub trait TransportManager {
 
 impl Swarm {
     pub fn new(ice_servers: &str, address: Address, session_manager: SessionManager) -> Self {
+        Self::new_with_max_connections(ice_servers, address, session_manager, None)
+    }
+
+    /// Create a Swarm which holds at most `max_connections` transports.
+    pub fn new_with_max_connections(
+        ice_servers: &str,
+        address: Address,
+        session_manager: SessionManager,
+        max_connections: Option<usize>,
+    ) -> Self {
         let ice_servers = ice_servers
             .split(';')
             .collect::<Vec<&str>>()
@@ -67,11 +91,15 @@ impl Swarm {
             .collect::<Vec<IceServer>>();
         Self {
             table: MemStorage::<Address, Arc<Transport>>::new(),
+            activity: MemStorage::<Address, u128>::new(),
+            max_connections,
             transport_event_channel: Channel::new(),
             ice_servers,
             address,
             session_manager,
             pending: Arc::new(Mutex::new(vec![])),
+            event_bus: EventBus::new(),
+            metrics: Arc::new(Metrics::new()),
         }
     }
 
@@ -83,16 +111,61 @@ impl Swarm {
         &self.session_manager
     }
 
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    /// Counters of swarm and message handling.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Subscribe events of swarm and dht.
+    pub fn subscribe(&self) -> EventReceiver {
+        self.event_bus.subscribe()
+    }
+
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+
+    /// Check if the number of transports in table has reached `max_connections`.
+    pub fn is_full(&self) -> bool {
+        self.max_connections
+            .map(|max| self.get_transport_numbers() >= max)
+            .unwrap_or(false)
+    }
+
+    /// Record that a message was exchanged with an address just now.
+    pub fn touch(&self, address: &Address) {
+        if self.table.get(address).is_some() {
+            self.activity.set(address, utils::get_epoch_ms());
+        }
+    }
+
+    /// Get the last time (epoch ms) a message was exchanged with an address.
+    pub fn last_active(&self, address: &Address) -> Option<u128> {
+        self.activity.get(address)
+    }
+
     fn load_message(&self, ev: Result<Option<Event>>) -> Result<Option<MessagePayload<Message>>> {
         let ev = ev?;
 
         match ev {
             Some(Event::DataChannelMessage(msg)) => {
-                let payload = MessagePayload::from_encoded(&msg.try_into()?)?;
+                let payload: MessagePayload<Message> = msg
+                    .try_into()
+                    .and_then(|encoded: message::Encoded| MessagePayload::from_encoded(&encoded))
+                    .map_err(|e| {
+                        self.metrics.inc_decode_errors();
+                        e
+                    })?;
+                self.touch(&payload.addr);
                 Ok(Some(payload))
             }
             Some(Event::RegisterTransport(address)) => match self.get_transport(&address) {
                 Some(_) => {
+                    self.event_bus.publish(RingEvent::PeerConnected(address));
                     let payload = MessagePayload::new_direct(
                         Message::JoinDHT(message::JoinDHT { id: address.into() }),
                         &self.session_manager,
@@ -148,7 +221,7 @@ impl Swarm {
             .pending
             .try_lock()
             .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
-        pending.push(transport.to_owned());
+        pending.push((transport.to_owned(), utils::get_epoch_ms()));
         Ok(())
     }
 
@@ -159,7 +232,7 @@ impl Swarm {
             .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
         let index = pending
             .iter()
-            .position(|x| x.id.eq(&transport_id))
+            .position(|(x, _)| x.id.eq(&transport_id))
             .ok_or(Error::SwarmPendingTransNotFound)?;
         pending.remove(index);
         Ok(())
@@ -170,7 +243,7 @@ impl Swarm {
             .pending
             .try_lock()
             .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
-        Ok(pending.iter().cloned().collect::<Vec<_>>())
+        Ok(pending.iter().map(|(x, _)| x).cloned().collect::<Vec<_>>())
     }
 
     pub fn find_pending_transport(&self, id: uuid::Uuid) -> Result<Option<Arc<Transport>>> {
@@ -178,7 +251,71 @@ impl Swarm {
             .pending
             .try_lock()
             .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
-        Ok(pending.iter().find(|x| x.id.eq(&id)).cloned())
+        Ok(pending
+            .iter()
+            .find(|(x, _)| x.id.eq(&id))
+            .map(|(x, _)| x)
+            .cloned())
+    }
+
+    /// Get how long (in ms) a pending transport has been waiting.
+    pub fn pending_transport_age(&self, id: uuid::Uuid) -> Result<Option<u128>> {
+        let pending = self
+            .pending
+            .try_lock()
+            .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
+        let now = utils::get_epoch_ms();
+        Ok(pending
+            .iter()
+            .find(|(x, _)| x.id.eq(&id))
+            .map(|(_, created_at)| now.saturating_sub(*created_at)))
+    }
+
+    /// Close and pop pending transports which have been waiting longer than `ttl_ms`,
+    /// return the number of collected transports.
+    pub async fn collect_stale_pending_transports(&self, ttl_ms: u128) -> Result<usize> {
+        let stale = {
+            let mut pending = self
+                .pending
+                .try_lock()
+                .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
+            let now = utils::get_epoch_ms();
+            let (stale, alive) = pending
+                .drain(..)
+                .partition::<Vec<_>, _>(|(_, created_at)| now.saturating_sub(*created_at) > ttl_ms);
+            *pending = alive;
+            stale
+        };
+        for (trans, _) in stale.iter() {
+            log::info!("close stale pending transport {}", trans.id);
+            if let Err(e) = trans.close().await {
+                log::warn!("failed to close stale pending transport: {}", e);
+            }
+        }
+        Ok(stale.len())
+    }
+
+    /// Close and remove all transports, including pending ones, for shutting down.
+    pub async fn close_all_transports(&self) -> Result<()> {
+        let pending = {
+            let mut pending = self
+                .pending
+                .try_lock()
+                .map_err(|_| Error::SwarmPendingTransTryLockFailed)?;
+            pending.drain(..).collect::<Vec<_>>()
+        };
+        for (trans, _) in pending.iter() {
+            if let Err(e) = trans.close().await {
+                log::warn!("failed to close pending transport {}: {}", trans.id, e);
+            }
+        }
+        for (address, trans) in self.get_transports() {
+            if let Err(e) = trans.close().await {
+                log::warn!("failed to close transport of {:?}: {}", address, e);
+            }
+            self.remove_transport(&address);
+        }
+        Ok(())
     }
 }
 
@@ -202,8 +339,14 @@ impl TransportManager for Swarm {
     /// register to swarm table
     /// should not wait connection statues here
     /// a connection `Promise` may cause deadlock of both end
+    /// a registered transport is no longer pending, so it won't be collected as stale
     async fn register(&self, address: &Address, trans: Self::Transport) -> Result<()> {
+        self.pending
+            .try_lock()
+            .map_err(|_| Error::SwarmPendingTransTryLockFailed)?
+            .retain(|(x, _)| x.id != trans.id);
         let prev_transport = self.table.set(address, trans);
+        self.activity.set(address, utils::get_epoch_ms());
         if let Some(transport) = prev_transport {
             if let Err(e) = transport.close().await {
                 log::error!("failed to close previous while registering {:?}", e);
@@ -219,7 +362,13 @@ impl TransportManager for Swarm {
     }
 
     fn remove_transport(&self, address: &Address) -> Option<(Address, Self::Transport)> {
-        self.table.remove(address)
+        self.activity.remove(address);
+        let removed = self.table.remove(address);
+        if removed.is_some() {
+            self.event_bus
+                .publish(RingEvent::PeerDisconnected(address.to_owned()));
+        }
+        removed
     }
 
     fn get_transport_numbers(&self) -> usize {
@@ -239,7 +388,11 @@ impl TransportManager for Swarm {
         address: &Address,
         default: Self::Transport,
     ) -> Result<Self::Transport> {
-        Ok(self.table.get_or_set(address, default))
+        let transport = self.table.get_or_set(address, default);
+        if self.activity.get(address).is_none() {
+            self.activity.set(address, utils::get_epoch_ms());
+        }
+        Ok(transport)
     }
 }
 
@@ -265,9 +418,105 @@ where T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static + fmt::Deb
         let transport = self
             .get_transport(address)
             .ok_or(Error::SwarmMissAddressInTable)?;
-        let data: Vec<u8> = payload.encode()?.into();
+        let data: Vec<u8> = payload
+            .encode()
+            .map_err(|e| {
+                self.metrics.inc_encode_errors();
+                e
+            })?
+            .into();
         transport.wait_for_data_channel_open().await?;
-        transport.send_message(data.as_slice()).await
+        transport.send_message(data.as_slice()).await?;
+        self.touch(address);
+        Ok(())
+    }
+}
+
+/// Background task to close pending transports not answered within `timeout` seconds.
+#[derive(Clone)]
+pub struct PendingTransportCollector {
+    swarm: Arc<Swarm>,
+    timeout: usize,
+}
+
+#[cfg_attr(feature = "wasm", async_trait(?Send))]
+#[cfg_attr(not(feature = "wasm"), async_trait)]
+pub trait TCollect {
+    async fn wait(self: Arc<Self>);
+}
+
+impl PendingTransportCollector {
+    pub fn new(swarm: Arc<Swarm>, timeout: usize) -> Self {
+        Self { swarm, timeout }
+    }
+
+    pub fn get_timeout(&self) -> usize {
+        self.timeout
+    }
+
+    /// Check pending transports several times in a timeout period,
+    /// so a stale one will not live much longer than `timeout`.
+    fn interval_ms(&self) -> u64 {
+        (self.timeout as u64 * 1000 / 4).max(1000)
+    }
+
+    pub async fn collect(&self) -> Result<usize> {
+        self.swarm
+            .collect_stale_pending_transports(self.timeout as u128 * 1000)
+            .await
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+mod collector {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use futures_timer::Delay;
+
+    use super::PendingTransportCollector;
+    use super::TCollect;
+
+    #[async_trait]
+    impl TCollect for PendingTransportCollector {
+        async fn wait(self: Arc<Self>) {
+            loop {
+                Delay::new(Duration::from_millis(self.interval_ms())).await;
+                if let Err(e) = self.collect().await {
+                    log::error!("failed to collect pending transports {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+mod collector {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use wasm_bindgen_futures::spawn_local;
+
+    use super::PendingTransportCollector;
+    use super::TCollect;
+    use crate::poll;
+
+    #[async_trait(?Send)]
+    impl TCollect for PendingTransportCollector {
+        async fn wait(self: Arc<Self>) {
+            let caller = Arc::clone(&self);
+            let interval = self.interval_ms() as i32;
+            let func = move || {
+                let caller = caller.clone();
+                spawn_local(Box::pin(async move {
+                    if let Err(e) = caller.collect().await {
+                        log::error!("failed to collect pending transports {:?}", e);
+                    }
+                }))
+            };
+            poll!(func, interval);
+        }
     }
 }
 
@@ -300,6 +549,49 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_collect_stale_pending_transports() -> Result<()> {
+        let swarm = Arc::new(new_swarm());
+        let stale = swarm.new_transport().await?;
+        swarm.push_pending_transport(&stale)?;
+        time::sleep(time::Duration::from_millis(1500)).await;
+        let fresh = swarm.new_transport().await?;
+        swarm.push_pending_transport(&fresh)?;
+
+        assert!(swarm.pending_transport_age(stale.id)?.unwrap() >= 1500);
+        assert!(swarm.pending_transport_age(fresh.id)?.unwrap() < 1500);
+
+        let collector = PendingTransportCollector::new(swarm.clone(), 1);
+        assert_eq!(collector.collect().await?, 1);
+        assert!(swarm.find_pending_transport(stale.id)?.is_none());
+        assert!(swarm.find_pending_transport(fresh.id)?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_registered_transport_is_not_collected() -> Result<()> {
+        let swarm1 = Arc::new(new_swarm());
+        let swarm2 = new_swarm();
+        let transport1 = swarm1.new_transport().await?;
+        let transport2 = swarm2.new_transport().await?;
+        swarm1.push_pending_transport(&transport1)?;
+
+        establish_connection(&transport1, &transport2).await?;
+        swarm1
+            .register(&swarm2.address(), transport1.clone())
+            .await?;
+        assert!(swarm1.find_pending_transport(transport1.id)?.is_none());
+
+        time::sleep(time::Duration::from_millis(1500)).await;
+        let collector = PendingTransportCollector::new(swarm1.clone(), 1);
+        assert_eq!(collector.collect().await?, 0);
+        assert_eq!(
+            transport1.ice_connection_state().await.unwrap(),
+            RTCIceConnectionState::Connected
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_swarm_register_and_get() -> Result<()> {
         let swarm1 = new_swarm();
@@ -48,6 +48,7 @@ pub struct DefaultTransport {
     data_channel: Arc<FuturesMutex<Option<Arc<RTCDataChannel>>>>,
     event_sender: EventSender,
     public_key: Arc<AsyncRwLock<Option<PublicKey>>>,
+    session_public_key: Arc<AsyncRwLock<Option<PublicKey>>>,
 }
 
 impl PartialEq for DefaultTransport {
@@ -78,6 +79,7 @@ impl IceTransport<Event, AcChannel<Event>> for DefaultTransport {
             pending_candidates: Arc::new(FuturesMutex::new(vec![])),
             data_channel: Arc::new(FuturesMutex::new(None)),
             public_key: Arc::new(AsyncRwLock::new(None)),
+            session_public_key: Arc::new(AsyncRwLock::new(None)),
             event_sender,
         }
     }
@@ -130,6 +132,10 @@ impl IceTransport<Event, AcChannel<Event>> for DefaultTransport {
         self.public_key.read().await.unwrap()
     }
 
+    async fn session_pubkey(&self) -> Option<PublicKey> {
+        *self.session_public_key.read().await
+    }
+
     async fn get_peer_connection(&self) -> Option<Arc<RTCPeerConnection>> {
         self.connection.lock().await.clone()
     }
@@ -435,6 +441,10 @@ impl IceTrickleScheme<Event, AcChannel<Event>> for DefaultTransport {
                     let mut pk = self.public_key.write().await;
                     *pk = Some(public_key);
                 };
+                if let Ok(public_key) = data.origin_verification.session_pubkey(&data.data) {
+                    let mut pk = self.session_public_key.write().await;
+                    *pk = Some(public_key);
+                };
                 Ok(data.addr)
             }
             _ => {
@@ -586,6 +596,11 @@ pub mod tests {
         // Peer 2 got offer then register
         let addr1 = transport2.register_remote_info(handshake_info1).await?;
         assert_eq!(addr1, key1.address());
+        assert_eq!(transport2.pubkey().await, key1.pubkey());
+        assert_eq!(
+            transport2.session_pubkey().await,
+            Some(sm1.session_key()?.pubkey())
+        );
         assert_eq!(
             transport1.ice_connection_state().await,
             Some(RTCIceConnectionState::New)
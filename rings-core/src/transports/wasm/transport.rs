@@ -56,6 +56,7 @@ pub struct WasmTransport {
     channel: Option<Arc<RtcDataChannel>>,
     event_sender: EventSender,
     public_key: Arc<RwLock<Option<PublicKey>>>,
+    session_public_key: Arc<RwLock<Option<PublicKey>>>,
 }
 
 impl PartialEq for WasmTransport {
@@ -80,6 +81,7 @@ impl IceTransport<Event, CbChannel<Event>> for WasmTransport {
             pending_candidates: Arc::new(Mutex::new(vec![])),
             channel: None,
             public_key: Arc::new(RwLock::new(None)),
+            session_public_key: Arc::new(RwLock::new(None)),
             event_sender,
         }
     }
@@ -118,6 +120,10 @@ impl IceTransport<Event, CbChannel<Event>> for WasmTransport {
         self.public_key.read().unwrap().unwrap()
     }
 
+    async fn session_pubkey(&self) -> Option<PublicKey> {
+        *self.session_public_key.read().unwrap()
+    }
+
     async fn ice_connection_state(&self) -> Option<Self::IceConnectionState> {
         self.get_peer_connection()
             .await
@@ -476,6 +482,10 @@ impl IceTrickleScheme<Event, CbChannel<Event>> for WasmTransport {
                     let mut pk = self.public_key.write().unwrap();
                     *pk = Some(public_key);
                 };
+                if let Ok(public_key) = data.origin_verification.session_pubkey(&data.data) {
+                    let mut pk = self.session_public_key.write().unwrap();
+                    *pk = Some(public_key);
+                };
                 let sdp: RtcSessionDescriptionWrapper = data.data.sdp.try_into()?;
                 self.set_remote_description(sdp.to_owned()).await?;
                 for c in &data.data.candidates {
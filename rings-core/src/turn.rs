@@ -0,0 +1,143 @@
+#![warn(missing_docs)]
+//! Time-limited credentials of TURN server, in the scheme of TURN REST API.
+//!
+//! Username is `<expiry>:<user>`, where `expiry` is a unix timestamp in seconds and `user`
+//! is the address of the peer it's issued to. Password is the base64 encoded HMAC-SHA1 of
+//! username, keyed by a secret shared between the issuer and TURN server, so TURN server
+//! can check credentials without any state.
+use hmac::Hmac;
+use hmac::Mac;
+use serde::Deserialize;
+use serde::Serialize;
+use sha1::Sha1;
+use web3::types::Address;
+
+use crate::utils;
+
+/// A credential of TURN server.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TurnCredential {
+    /// urls of TURN server, in format `turn://<host>:<port>`
+    pub urls: Vec<String>,
+    /// `<expiry>:<user>`
+    pub username: String,
+    /// HMAC of username
+    pub credential: String,
+    /// unix timestamp in seconds, after which the credential is rejected
+    pub expires_at: u64,
+}
+
+impl TurnCredential {
+    /// Return true if the credential is expired.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now()
+    }
+
+    /// Urls with username and credential embedded, in format of [crate::swarm::Swarm::new].
+    pub fn ice_server_urls(&self) -> Vec<String> {
+        self.urls
+            .iter()
+            .filter_map(|url| {
+                let mut url = url::Url::parse(url).ok()?;
+                url.set_username(&self.username).ok()?;
+                url.set_password(Some(&self.credential)).ok()?;
+                Some(url.to_string())
+            })
+            .collect()
+    }
+}
+
+/// Issue credentials of a TURN server, with secret shared with it.
+#[derive(Debug, Clone)]
+pub struct TurnCredentialIssuer {
+    urls: Vec<String>,
+    secret: String,
+    ttl: u64,
+}
+
+impl TurnCredentialIssuer {
+    /// Create an issuer
+    /// * urls: urls of TURN server
+    /// * secret: secret shared with TURN server
+    /// * ttl: seconds before an issued credential expires
+    pub fn new(urls: Vec<String>, secret: &str, ttl: u64) -> Self {
+        Self {
+            urls,
+            secret: secret.to_owned(),
+            ttl,
+        }
+    }
+
+    /// Issue a credential to `user`.
+    pub fn issue(&self, user: &Address) -> TurnCredential {
+        let expires_at = now() + self.ttl;
+        let username = format!("{}:{:?}", expires_at, user);
+        TurnCredential {
+            urls: self.urls.clone(),
+            credential: credential(&self.secret, &username),
+            username,
+            expires_at,
+        }
+    }
+}
+
+/// Password of `username`, keyed by `secret`.
+pub fn credential(secret: &str, username: &str) -> String {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(username.as_bytes());
+    base64::encode(mac.finalize().into_bytes())
+}
+
+/// Split username into expiry and user, return None if it's malformed.
+pub fn parse_username(username: &str) -> Option<(u64, &str)> {
+    let (expires_at, user) = username.split_once(':')?;
+    Some((expires_at.parse().ok()?, user))
+}
+
+fn now() -> u64 {
+    (utils::get_epoch_ms() / 1000) as u64
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::ecc::SecretKey;
+    use crate::types::ice_transport::IceServer;
+
+    #[test]
+    fn test_issue_credential() {
+        let address = SecretKey::random().address();
+        let issuer =
+            TurnCredentialIssuer::new(vec!["turn://127.0.0.1:3478".to_owned()], "secret", 60);
+        let cred = issuer.issue(&address);
+        assert!(!cred.is_expired());
+
+        let (expires_at, user) = parse_username(&cred.username).unwrap();
+        assert_eq!(expires_at, cred.expires_at);
+        assert_eq!(user, format!("{:?}", address));
+        assert_eq!(cred.credential, credential("secret", &cred.username));
+        assert_ne!(cred.credential, credential("other", &cred.username));
+        let server = IceServer::from_str(&cred.ice_server_urls()[0]).unwrap();
+        assert_eq!(server.urls, vec!["turn:127.0.0.1:3478".to_owned()]);
+        assert_eq!(server.username, cred.username);
+        assert_eq!(server.credential, cred.credential);
+
+        assert!(parse_username("rings").is_none());
+        assert!(parse_username("abc:0x00").is_none());
+    }
+
+    #[test]
+    fn test_credential_vector() {
+        // HMAC-SHA1 computed by python's hmac module
+        assert_eq!(
+            credential(
+                "secret",
+                "1234567890:0x11e807fcc88dd319270493fb2e822e388fe36ab0"
+            ),
+            "CU7OcqHcX31XTHFBnOiq0n0ViMs="
+        );
+    }
+}
@@ -46,6 +46,8 @@ pub trait IceTransport<E: Send, Ch: Channel<E>> {
     async fn ice_connection_state(&self) -> Option<Self::IceConnectionState>;
     async fn is_connected(&self) -> bool;
     async fn pubkey(&self) -> PublicKey;
+    /// Public key of session which signed the handshake of remote peer.
+    async fn session_pubkey(&self) -> Option<PublicKey>;
     async fn get_peer_connection(&self) -> Option<Arc<Self::Connection>>;
     async fn get_pending_candidates(&self) -> Vec<Self::Candidate>;
     async fn get_answer(&self) -> Result<Self::Sdp>;
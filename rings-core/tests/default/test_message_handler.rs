@@ -5,6 +5,7 @@ pub mod test {
 
     use futures::lock::Mutex;
     use rings_core::dht::vnode::VirtualNode;
+    use rings_core::dht::Chord;
     use rings_core::dht::Did;
     use rings_core::dht::PeerRing;
     use rings_core::ecc::SecretKey;
@@ -14,10 +15,15 @@ pub mod test {
     use rings_core::message::Message;
     use rings_core::message::MessageHandler;
     use rings_core::message::PayloadSender;
+    use rings_core::message::PingOperator;
+    use rings_core::message::RoutingTableOperator;
+    use rings_core::message::TurnCredentialOperator;
     use rings_core::session::SessionManager;
     use rings_core::swarm::Swarm;
     use rings_core::swarm::TransportManager;
     use rings_core::transports::Transport;
+    use rings_core::turn;
+    use rings_core::turn::TurnCredentialIssuer;
     use rings_core::types::ice_transport::IceTransport;
     use rings_core::types::ice_transport::IceTrickleScheme;
     use rings_core::types::message::MessageListener;
@@ -590,4 +596,171 @@ pub mod test {
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_handle_turn_credential() -> Result<()> {
+        let key1 = SecretKey::random();
+        let key2 = SecretKey::random();
+        let dht1 = Arc::new(Mutex::new(new_chord(key1.address().into())));
+        let dht2 = Arc::new(Mutex::new(new_chord(key2.address().into())));
+        let swarm1 = Arc::new(new_swarm(&key1));
+        let swarm2 = Arc::new(new_swarm(&key2));
+        let (_, _) = establish_connection(Arc::clone(&swarm1), Arc::clone(&swarm2)).await?;
+
+        let handler1 = MessageHandler::new(Arc::clone(&dht1), Arc::clone(&swarm1));
+        let handler2 = MessageHandler::new(Arc::clone(&dht2), Arc::clone(&swarm2));
+        handler2
+            .set_turn_issuer(TurnCredentialIssuer::new(
+                vec!["turn://127.0.0.1:3478".to_owned()],
+                "secret",
+                60,
+            ))
+            .await;
+        tokio::select! {
+             _ = async {
+                 futures::join!(
+                     async {
+                         loop {
+                             Arc::new(handler1.clone()).listen().await;
+                         }
+                     },
+                     async {
+                         loop {
+                             Arc::new(handler2.clone()).listen().await;
+                         }
+                     }
+                 );
+             } => { unreachable!();}
+             _ = async {
+                 let transport_1_to_2 = swarm1.get_transport(&swarm2.address()).unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 transport_1_to_2.wait_for_data_channel_open().await.unwrap();
+                 assert!(handler1.turn_credential(&swarm2.address()).is_none());
+                 handler1.request_turn_credential(&swarm2.address()).await.unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 let credential = handler1.turn_credential(&swarm2.address()).unwrap();
+                 assert_eq!(
+                     turn::parse_username(&credential.username).unwrap().1,
+                     format!("{:?}", swarm1.address())
+                 );
+                 assert_eq!(credential.credential, turn::credential("secret", &credential.username));
+
+                 // handler1 has no TURN server, so request of handler2 is ignored
+                 handler2.request_turn_credential(&swarm1.address()).await.unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 assert!(handler2.turn_credential(&swarm1.address()).is_none());
+             } => {}
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handle_ping() -> Result<()> {
+        let key1 = SecretKey::random();
+        let key2 = SecretKey::random();
+        let did1: Did = key1.address().into();
+        let did2: Did = key2.address().into();
+        let dht1 = Arc::new(Mutex::new(new_chord(did1)));
+        let dht2 = Arc::new(Mutex::new(new_chord(did2)));
+        let swarm1 = Arc::new(new_swarm(&key1));
+        let swarm2 = Arc::new(new_swarm(&key2));
+        let (_, _) = establish_connection(Arc::clone(&swarm1), Arc::clone(&swarm2)).await?;
+        dht1.lock().await.join(did2);
+        dht2.lock().await.join(did1);
+
+        let handler1 = MessageHandler::new(Arc::clone(&dht1), Arc::clone(&swarm1));
+        let handler2 = MessageHandler::new(Arc::clone(&dht2), Arc::clone(&swarm2));
+        tokio::select! {
+             _ = async {
+                 futures::join!(
+                     async {
+                         loop {
+                             Arc::new(handler1.clone()).listen().await;
+                         }
+                     },
+                     async {
+                         loop {
+                             Arc::new(handler2.clone()).listen().await;
+                         }
+                     }
+                 );
+             } => { unreachable!();}
+             _ = async {
+                 let transport_1_to_2 = swarm1.get_transport(&swarm2.address()).unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 transport_1_to_2.wait_for_data_channel_open().await.unwrap();
+
+                 // local node is responsible for itself, so it's reported at once
+                 let id = handler1.ping(&did1).await.unwrap();
+                 let report = handler1.take_ping_report(id).unwrap();
+                 assert_eq!(report.hops.len(), 1);
+                 assert!(handler1.take_ping_report(id).is_none());
+
+                 let id = handler1.ping(&did2).await.unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 let report = handler1.take_ping_report(id).unwrap();
+                 assert_eq!(report.target, did2);
+                 let path = report.hops.iter().map(|h| h.did).collect::<Vec<_>>();
+                 assert_eq!(path, vec![did1, did2, did1]);
+                 assert!(report.hops.windows(2).all(|w| w[0].ts_ms <= w[1].ts_ms));
+             } => {}
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handle_get_routing_table() -> Result<()> {
+        let key1 = SecretKey::random();
+        let key2 = SecretKey::random();
+        let did1: Did = key1.address().into();
+        let did2: Did = key2.address().into();
+        let dht1 = Arc::new(Mutex::new(new_chord(did1)));
+        let dht2 = Arc::new(Mutex::new(new_chord(did2)));
+        let swarm1 = Arc::new(new_swarm(&key1));
+        let swarm2 = Arc::new(new_swarm(&key2));
+        let (_, _) = establish_connection(Arc::clone(&swarm1), Arc::clone(&swarm2)).await?;
+        dht1.lock().await.join(did2);
+        dht2.lock().await.join(did1);
+        dht2.lock().await.predecessor = Some(did1);
+
+        let handler1 = MessageHandler::new(Arc::clone(&dht1), Arc::clone(&swarm1));
+        let handler2 = MessageHandler::new(Arc::clone(&dht2), Arc::clone(&swarm2));
+        tokio::select! {
+             _ = async {
+                 futures::join!(
+                     async {
+                         loop {
+                             Arc::new(handler1.clone()).listen().await;
+                         }
+                     },
+                     async {
+                         loop {
+                             Arc::new(handler2.clone()).listen().await;
+                         }
+                     }
+                 );
+             } => { unreachable!();}
+             _ = async {
+                 let transport_1_to_2 = swarm1.get_transport(&swarm2.address()).unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 transport_1_to_2.wait_for_data_channel_open().await.unwrap();
+
+                 let id = handler1.request_routing_table(&did1).await.unwrap();
+                 let report = handler1.take_routing_table(id).unwrap();
+                 assert_eq!(report.did, did1);
+                 assert_eq!(report.successors, vec![did2]);
+
+                 let id = handler1.request_routing_table(&did2).await.unwrap();
+                 sleep(Duration::from_millis(1000)).await;
+                 let report = handler1.take_routing_table(id).unwrap();
+                 assert_eq!(report.target, did2);
+                 assert_eq!(report.did, did2);
+                 assert_eq!(report.predecessor, Some(did1));
+                 assert_eq!(report.successors, vec![did1]);
+                 assert_eq!(report.finger, vec![did1]);
+                 assert!(handler1.take_routing_table(id).is_none());
+             } => {}
+        }
+        Ok(())
+    }
 }
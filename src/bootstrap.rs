@@ -0,0 +1,277 @@
+#![warn(missing_docs)]
+//! Connect bootstrap peers on start, and again whenever the node is left without connected peers.
+//!
+//! Joining the ring needs no extra step: once a transport is registered,
+//! swarm emits `JoinDHT` and the node looks up its successor through the new peer.
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::prelude::rings_core::prelude::url::Url;
+use crate::prelude::rings_core::prelude::web3::types::Address;
+use crate::prelude::rings_core::swarm::TransportManager;
+use crate::prelude::rings_core::transports::Transport;
+use crate::prelude::rings_core::types::ice_transport::IceTransport;
+use crate::processor::Processor;
+
+/// Max time to wait for a new transport to be connected.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+/// Interval to check state of a new transport.
+const CONNECT_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A bootstrap peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootstrapPeer {
+    /// jsonrpc endpoint of a node, connected by `connectPeerViaHttp`
+    Http(String),
+    /// address of a node, connected through transports already established,
+    /// or through its record in registry. Without registry, it's tried only after
+    /// an url peer is connected.
+    Address(Address),
+}
+
+impl FromStr for BootstrapPeer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Url::parse(s).map_err(|_| Error::InvalidBootstrapPeer(s.to_owned()))?;
+            return Ok(Self::Http(s.to_owned()));
+        }
+        Address::from_str(s)
+            .map(Self::Address)
+            .map_err(|_| Error::InvalidBootstrapPeer(s.to_owned()))
+    }
+}
+
+impl fmt::Display for BootstrapPeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(url) => write!(f, "{}", url),
+            Self::Address(address) => write!(f, "{:?}", address),
+        }
+    }
+}
+
+/// Bootstrap of a node.
+pub struct Bootstrap {
+    processor: Processor,
+    peers: Vec<BootstrapPeer>,
+    retries: usize,
+    retry_interval: Duration,
+    rejoin_interval: Duration,
+}
+
+impl Bootstrap {
+    /// Create a Bootstrap
+    /// * retries: times to retry each peer after the first failure
+    /// * retry_interval: seconds between retries
+    /// * rejoin_interval: seconds between checks of connected peers
+    pub fn new(
+        processor: Processor,
+        peers: Vec<BootstrapPeer>,
+        retries: usize,
+        retry_interval: usize,
+        rejoin_interval: usize,
+    ) -> Self {
+        Self {
+            processor,
+            peers,
+            retries,
+            retry_interval: Duration::from_secs(retry_interval as u64),
+            rejoin_interval: Duration::from_secs(rejoin_interval as u64),
+        }
+    }
+
+    /// Try url peers in order until one is connected, then connect address peers.
+    /// Address peers are reached through established transports, so without registry
+    /// they are skipped if no url peer is connected.
+    /// Return the first peer connected.
+    pub async fn join(&self) -> Result<BootstrapPeer> {
+        let (urls, addresses): (Vec<_>, Vec<_>) = self
+            .peers
+            .iter()
+            .partition(|p| matches!(p, BootstrapPeer::Http(_)));
+        let mut connected = None;
+        for peer in urls {
+            if self.connect_with_retries(peer).await {
+                connected = Some(peer.clone());
+                break;
+            }
+        }
+        if connected.is_none() && self.processor.registry.is_none() {
+            return Err(Error::BootstrapFailed);
+        }
+        for peer in addresses {
+            if self.connect_with_retries(peer).await && connected.is_none() {
+                connected = Some(peer.clone());
+            }
+        }
+        connected.ok_or(Error::BootstrapFailed)
+    }
+
+    /// Connect a peer, retry it on failure, return true if it's connected.
+    async fn connect_with_retries(&self, peer: &BootstrapPeer) -> bool {
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.retry_interval).await;
+            }
+            match self.connect(peer).await {
+                Ok(()) => {
+                    log::info!("connected bootstrap peer {}", peer);
+                    return true;
+                }
+                Err(e) => log::warn!(
+                    "connect bootstrap peer {} failed ({}/{}): {}",
+                    peer,
+                    attempt + 1,
+                    self.retries + 1,
+                    e
+                ),
+            }
+        }
+        false
+    }
+
+    async fn connect(&self, peer: &BootstrapPeer) -> Result<()> {
+        let transport = match peer {
+            BootstrapPeer::Http(url) => self.processor.connect_peer_via_http(url).await?,
+            BootstrapPeer::Address(address) => {
+                self.processor
+                    .connect_with_address(address, false)
+                    .await?
+                    .transport
+            }
+        };
+        let wait_connected = async {
+            while !transport.is_connected().await {
+                tokio::time::sleep(CONNECT_CHECK_INTERVAL).await;
+            }
+        };
+        if tokio::time::timeout(CONNECT_TIMEOUT, wait_connected)
+            .await
+            .is_err()
+        {
+            self.drop_transport(&transport).await;
+            return Err(Error::BootstrapTimeout);
+        }
+        Ok(())
+    }
+
+    /// Close a transport not connected in time, and remove it from swarm,
+    /// so the half-open transport is not taken as a peer.
+    async fn drop_transport(&self, transport: &Arc<Transport>) {
+        for (address, t) in self.processor.swarm.get_transports() {
+            if Arc::ptr_eq(&t, transport) {
+                self.processor.swarm.remove_transport(&address);
+            }
+        }
+        if let Err(e) = transport.close().await {
+            log::warn!("failed to close bootstrap transport: {}", e);
+        }
+    }
+
+    /// Check if any transport of swarm is connected.
+    async fn has_connected_peer(&self) -> bool {
+        for (_, transport) in self.processor.swarm.get_transports() {
+            if transport.is_connected().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Join on start, then rejoin whenever there is no connected peer left.
+    pub async fn wait(self: Arc<Self>) {
+        if self.peers.is_empty() {
+            return;
+        }
+        loop {
+            if !self.has_connected_peer().await {
+                if let Err(e) = self.join().await {
+                    log::error!("bootstrap failed: {}", e);
+                }
+            }
+            tokio::time::sleep(self.rejoin_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::lock::Mutex;
+
+    use super::*;
+    use crate::prelude::rings_core::dht::Stabilization;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_parse_bootstrap_peer() {
+        let address = SecretKey::random().address();
+        assert_eq!(
+            BootstrapPeer::from_str("http://127.0.0.1:50000").unwrap(),
+            BootstrapPeer::Http("http://127.0.0.1:50000".to_owned())
+        );
+        assert_eq!(
+            BootstrapPeer::from_str(&format!("{:?}", address)).unwrap(),
+            BootstrapPeer::Address(address)
+        );
+        assert!(BootstrapPeer::from_str("127.0.0.1:50000").is_err());
+    }
+
+    fn new_processor() -> Processor {
+        let key = SecretKey::random();
+        let session = SessionManager::new_with_seckey(&key).unwrap();
+        let swarm = Arc::new(Swarm::new(
+            "stun://stun.l.google.com:19302",
+            key.address(),
+            session,
+        ));
+        let dht = Arc::new(Mutex::new(PeerRing::new(key.address().into())));
+        let msg_handler = Arc::new(MessageHandler::new(dht.clone(), swarm.clone()));
+        let stabilization = Arc::new(Stabilization::new(dht, swarm.clone(), 20));
+        (swarm, msg_handler, stabilization).into()
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_retries_peers_in_order() {
+        let processor = new_processor();
+        let peers = vec![
+            "http://127.0.0.1:1".parse().unwrap(),
+            format!("{:?}", SecretKey::random().address())
+                .parse()
+                .unwrap(),
+        ];
+        let bootstrap = Bootstrap::new(processor, peers, 1, 0, 30);
+        assert!(matches!(
+            bootstrap.join().await,
+            Err(Error::BootstrapFailed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_skips_address_peers_without_connected_peer() {
+        let processor = new_processor();
+        let peers = vec![format!("{:?}", SecretKey::random().address())
+            .parse()
+            .unwrap()];
+        let bootstrap = Bootstrap::new(processor.clone(), peers, 3, 10, 30);
+        // it fails at once, without waiting for retries
+        let joined = tokio::time::timeout(Duration::from_secs(1), bootstrap.join()).await;
+        assert!(matches!(joined, Ok(Err(Error::BootstrapFailed))));
+
+        // a registered transport doesn't count, until it's connected
+        let transport = processor.swarm.new_transport().await.unwrap();
+        processor
+            .swarm
+            .register(&SecretKey::random().address(), transport.clone())
+            .await
+            .unwrap();
+        assert!(!bootstrap.has_connected_peer().await);
+        bootstrap.drop_transport(&transport).await;
+        assert!(processor.swarm.get_transports().is_empty());
+    }
+}
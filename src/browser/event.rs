@@ -0,0 +1,167 @@
+//! Typed events of browser [super::Client], dispatched to listeners added by
+//! `add_event_listener`.
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::future::AbortHandle;
+
+use crate::prelude::js_sys;
+use crate::prelude::rings_core::dht::Did;
+use crate::prelude::rings_core::event::RingEvent;
+use crate::prelude::rings_core::prelude::web3::types::Address;
+use crate::prelude::wasm_bindgen;
+use crate::prelude::wasm_bindgen::prelude::*;
+use crate::prelude::web3::contract::tokens::Tokenizable;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_EVENTS: &'static str = r#"
+export type RingEventType = 'peerConnected' | 'peerDisconnected' | 'message' | 'dhtValue';
+
+export interface PeerConnectedEvent {
+  type: 'peerConnected';
+  address: string;
+}
+
+export interface PeerDisconnectedEvent {
+  type: 'peerDisconnected';
+  address: string;
+}
+
+export interface CustomMessageEvent {
+  type: 'message';
+  /** address of sender */
+  from: string;
+  /** decrypted body of custom message */
+  data: Uint8Array;
+}
+
+export interface DhtValueEvent {
+  type: 'dhtValue';
+  did: string;
+  /** `stored` on local node, or `fetched` into local cache */
+  action: 'stored' | 'fetched';
+}
+
+export type RingEvent = PeerConnectedEvent | PeerDisconnectedEvent | CustomMessageEvent | DhtValueEvent;
+
+export type RingEventListener = (ev: RingEvent) => void;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Name of event, see `RingEventType` in TypeScript definitions.
+    #[wasm_bindgen(typescript_type = "RingEventType")]
+    pub type RingEventType;
+
+    /// Callback invoked with each event, see `RingEventListener` in TypeScript definitions.
+    #[wasm_bindgen(typescript_type = "RingEventListener")]
+    pub type RingEventListener;
+}
+
+/// Events can be listened in browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    PeerConnected,
+    PeerDisconnected,
+    Message,
+    DhtValue,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::PeerConnected => "peerConnected",
+            Self::PeerDisconnected => "peerDisconnected",
+            Self::Message => "message",
+            Self::DhtValue => "dhtValue",
+        }
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = JsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "peerConnected" => Self::PeerConnected,
+            "peerDisconnected" => Self::PeerDisconnected,
+            "message" => Self::Message,
+            "dhtValue" => Self::DhtValue,
+            _ => return Err(JsError::new(&format!("unknown event type {}", s))),
+        })
+    }
+}
+
+fn address_to_string(address: &Address) -> String {
+    address.into_token().to_string()
+}
+
+fn did_to_string(did: &Did) -> String {
+    format!("{:?}", **did)
+}
+
+/// Kind and JS object of an event, None if it's not exposed to browser.
+pub fn to_js_event(ev: &RingEvent) -> Result<Option<(EventKind, JsValue)>, JsValue> {
+    let obj = js_sys::Object::new();
+    let set = |k: &str, v: &JsValue| js_sys::Reflect::set(&obj, &JsValue::from_str(k), v);
+    let kind = match ev {
+        RingEvent::PeerConnected(address) => {
+            set("address", &address_to_string(address).into())?;
+            EventKind::PeerConnected
+        }
+        RingEvent::PeerDisconnected(address) => {
+            set("address", &address_to_string(address).into())?;
+            EventKind::PeerDisconnected
+        }
+        RingEvent::CustomMessage { origin, data } => {
+            let from = Address::from(*origin);
+            set("from", &address_to_string(&from).into())?;
+            set("data", &js_sys::Uint8Array::from(data.as_slice()).into())?;
+            EventKind::Message
+        }
+        RingEvent::VNodeStored(did) | RingEvent::VNodeFetched(did) => {
+            let action = match ev {
+                RingEvent::VNodeStored(_) => "stored",
+                _ => "fetched",
+            };
+            set("did", &did_to_string(did).into())?;
+            set("action", &action.into())?;
+            EventKind::DhtValue
+        }
+        _ => return Ok(None),
+    };
+    set("type", &kind.as_str().into())?;
+    Ok(Some((kind, obj.into())))
+}
+
+/// Listeners added to a client, each of them can be removed by its id.
+#[derive(Clone, Default)]
+pub struct EventListeners {
+    next_id: Arc<AtomicU32>,
+    handles: Arc<Mutex<HashMap<u32, AbortHandle>>>,
+}
+
+impl EventListeners {
+    /// Register abort handle of a listener, return its id.
+    pub fn insert(&self, handle: AbortHandle) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.handles.lock().unwrap().insert(id, handle);
+        id
+    }
+
+    /// Stop listener of id, return false if it's not found.
+    pub fn remove(&self, id: u32) -> bool {
+        let handle = self.handles.lock().unwrap().remove(&id);
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
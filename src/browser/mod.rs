@@ -1,17 +1,26 @@
 //! rings-node browser support.
 #![allow(clippy::unused_unit)]
+pub mod event;
 pub mod utils;
 
 use std::str::FromStr;
 use std::sync::Arc;
 
 use futures::lock::Mutex;
+use futures::StreamExt;
 use js_sys::Promise;
 use rings_core_wasm::dht::TStabilize;
 use serde::Deserialize;
 use serde::Serialize;
 
+use self::event::EventKind;
+use self::event::EventListeners;
+use self::event::RingEventListener;
+use self::event::RingEventType;
 use self::utils::from_rtc_ice_connection_state;
+use crate::jsonrpc::response::FetchedValue;
+use crate::jsonrpc::response::StoredValue;
+use crate::jsonrpc::response::SubRingInfo;
 use crate::prelude::js_sys;
 use crate::prelude::rings_core::async_trait;
 use crate::prelude::rings_core::dht::PeerRing;
@@ -28,13 +37,16 @@ use crate::prelude::rings_core::prelude::web3::types::Address;
 use crate::prelude::rings_core::session::AuthorizedInfo;
 use crate::prelude::rings_core::session::SessionManager;
 use crate::prelude::rings_core::session::Signer;
+use crate::prelude::rings_core::swarm::PendingTransportCollector;
 use crate::prelude::rings_core::swarm::Swarm;
+use crate::prelude::rings_core::swarm::TCollect;
 use crate::prelude::rings_core::swarm::TransportManager;
 use crate::prelude::rings_core::transports::Transport;
 use crate::prelude::rings_core::types::ice_transport::IceTransport;
 use crate::prelude::rings_core::types::message::MessageListener;
 use crate::prelude::wasm_bindgen;
 use crate::prelude::wasm_bindgen::prelude::*;
+use crate::prelude::wasm_bindgen::JsCast;
 use crate::prelude::wasm_bindgen_futures;
 use crate::prelude::wasm_bindgen_futures::future_to_promise;
 use crate::prelude::web3::contract::tokens::Tokenizable;
@@ -42,6 +54,12 @@ use crate::prelude::web_sys::RtcIceConnectionState;
 use crate::processor;
 use crate::processor::Processor;
 
+/// Seconds before an unanswered pending transport is closed.
+const PENDING_TRANSPORT_TIMEOUT: usize = 60;
+/// How long `fetch` waits for a remote vnode to arrive in local cache.
+const FETCH_VALUE_TIMEOUT_MS: i32 = 3000;
+const FETCH_VALUE_INTERVAL_MS: i32 = 100;
+
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsError> {
     utils::set_panic_hook();
@@ -125,11 +143,14 @@ impl UnsignedInfo {
 /// const signed = await signer.signMessage(unsignedInfo.auth);
 /// const sig = new Uint8Array(web3.utils.hexToBytes(signed));
 /// const client = new Client(unsignedInfo, sig, stunOrTurnUrl);
+/// // or limit the number of connected peers
+/// const client = new Client(unsignedInfo, sig, stunOrTurnUrl, 32);
 /// ```
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct Client {
     processor: Arc<Processor>,
+    listeners: EventListeners,
 }
 
 #[wasm_bindgen]
@@ -139,16 +160,25 @@ impl Client {
         unsigned_info: &UnsignedInfo,
         signed_data: js_sys::Uint8Array,
         stuns: String,
+        max_connections: Option<usize>,
     ) -> Result<Client, JsError> {
         let random_key = unsigned_info.random_key;
         let session = SessionManager::new(&signed_data.to_vec(), &unsigned_info.auth, &random_key);
-        let swarm = Arc::new(Swarm::new(&stuns, unsigned_info.key_addr, session));
+        let swarm = Arc::new(Swarm::new_with_max_connections(
+            &stuns,
+            unsigned_info.key_addr,
+            session,
+            max_connections,
+        ));
         let pr = PeerRing::new(swarm.address().into());
         let dht = Arc::new(Mutex::new(pr));
         let msg_handler = Arc::new(MessageHandler::new(dht.clone(), swarm.clone()));
         let stabilization = Arc::new(Stabilization::new(dht, swarm.clone(), 20));
         let processor = Arc::new(Processor::from((swarm, msg_handler, stabilization)));
-        Ok(Client { processor })
+        Ok(Client {
+            processor,
+            listeners: EventListeners::default(),
+        })
     }
 
     /// start backgroud listener without custom callback
@@ -158,12 +188,19 @@ impl Client {
         future_to_promise(async move {
             let h = Arc::clone(&p.msg_handler);
             let s = Arc::clone(&p.stabilization);
+            let c = Arc::new(PendingTransportCollector::new(
+                p.swarm.clone(),
+                PENDING_TRANSPORT_TIMEOUT,
+            ));
             futures::join!(
                 async {
                     h.listen().await;
                 },
                 async {
                     s.wait().await;
+                },
+                async {
+                    c.wait().await;
                 }
             );
             Ok(JsValue::null())
@@ -200,6 +237,10 @@ impl Client {
         future_to_promise(async move {
             let h = Arc::clone(&p.msg_handler);
             let s = Arc::clone(&p.stabilization);
+            let c = Arc::new(PendingTransportCollector::new(
+                p.swarm.clone(),
+                PENDING_TRANSPORT_TIMEOUT,
+            ));
             h.set_callback(cb).await;
             futures::join!(
                 async {
@@ -207,6 +248,9 @@ impl Client {
                 },
                 async {
                     s.wait().await;
+                },
+                async {
+                    c.wait().await;
                 }
             );
             Ok(JsValue::null())
@@ -227,6 +271,19 @@ impl Client {
         })
     }
 
+    /// connect peer with signaling endpoint `POST /sdp` of remote node
+    pub fn connect_peer_via_signaling(&self, sdp_url: String, token: Option<String>) -> Promise {
+        log::debug!("sdp_url: {}", sdp_url);
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            let transport = p
+                .connect_peer_via_signaling(sdp_url.as_str(), token.as_deref())
+                .await
+                .map_err(JsError::from)?;
+            Ok(JsValue::from_str(transport.id.to_string().as_str()))
+        })
+    }
+
     /// connect peer with web3 address, without waiting for transport channel connected
     pub fn connect_with_address_without_wait(&self, address: String) -> Promise {
         let p = self.processor.clone();
@@ -371,6 +428,25 @@ impl Client {
         })
     }
 
+    /// request a credential of TURN server from a connected peer,
+    /// which is available by `turn_credential` after it's issued.
+    pub fn request_turn_credential(&self, address: String) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            p.request_turn_credential(address.as_str())
+                .await
+                .map_err(JsError::from)?;
+            Ok(JsValue::from_bool(true))
+        })
+    }
+
+    /// get credential of TURN server issued by peer, `null` if there is none or it's expired.
+    /// Its `urls`, `username` and `credential` are fields of an `RTCIceServer`.
+    pub fn turn_credential(&self, address: String) -> Result<JsValue, JsError> {
+        let credential = self.processor.turn_credential(address.as_str())?;
+        Ok(JsValue::from_serde(&credential)?)
+    }
+
     /// get peer by address
     pub fn get_peer(&self, address: String) -> Promise {
         let p = self.processor.clone();
@@ -414,6 +490,153 @@ impl Client {
             Ok(JsValue::null())
         })
     }
+
+    /// store a string value on DHT, resolve the did of vnode.
+    /// ```typescript
+    /// const did: string = await client.store('hello')
+    /// ```
+    pub fn store(&self, value: String) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            let did = p.store_value(&value).await.map_err(JsError::from)?;
+            Ok(JsValue::from_str(&StoredValue::from(did).did))
+        })
+    }
+
+    /// fetch a vnode from DHT with its did, resolve `null` if it's not found in time.
+    /// ```typescript
+    /// const vnode = await client.fetch(did)
+    /// // vnode: { did: '0x...', kind: 'Data', data: ['hello'] }
+    /// ```
+    pub fn fetch(&self, did: String) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            let mut vnode = p.fetch_value(&did).await.map_err(JsError::from)?;
+            let mut waited = 0;
+            while vnode.is_none() && waited < FETCH_VALUE_TIMEOUT_MS {
+                utils::sleep(FETCH_VALUE_INTERVAL_MS).await?;
+                waited += FETCH_VALUE_INTERVAL_MS;
+                vnode = p.check_cache(&did).await.map_err(JsError::from)?;
+            }
+            match vnode {
+                Some(vnode) => {
+                    let value = FetchedValue::try_from(vnode).map_err(JsError::from)?;
+                    Ok(JsValue::from_serde(&value).map_err(JsError::from)?)
+                }
+                None => Ok(JsValue::null()),
+            }
+        })
+    }
+
+    /// create a subring with name, resolve the did of subring.
+    pub fn create_subring(&self, name: String) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            let did = p.create_subring(&name).await.map_err(JsError::from)?;
+            Ok(JsValue::from_str(&StoredValue::from(did).did))
+        })
+    }
+
+    /// join a subring with name.
+    pub fn join_subring(&self, name: String) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            p.join_subring(&name).await.map_err(JsError::from)?;
+            Ok(JsValue::null())
+        })
+    }
+
+    /// list subrings stored on local node.
+    /// ```typescript
+    /// const rings = await client.list_subrings()
+    /// // rings: [{ name, did, creator, admin, members: ['0x...'] }]
+    /// ```
+    pub fn list_subrings(&self) -> Promise {
+        let p = self.processor.clone();
+        future_to_promise(async move {
+            let rings = p
+                .list_subrings()
+                .await
+                .into_iter()
+                .map(SubRingInfo::from)
+                .collect::<Vec<_>>();
+            Ok(JsValue::from_serde(&rings).map_err(JsError::from)?)
+        })
+    }
+
+    /// add a listener of typed events, return its id for `remove_event_listener`.
+    /// ```typescript
+    /// const id = client.add_event_listener('message', (ev: RingEvent) => {
+    ///   if (ev.type === 'message') {
+    ///     console.log(ev.from, new TextDecoder().decode(ev.data))
+    ///   }
+    /// })
+    /// client.remove_event_listener(id)
+    /// ```
+    pub fn add_event_listener(
+        &self,
+        event: RingEventType,
+        listener: RingEventListener,
+    ) -> Result<u32, JsError> {
+        let kind = event
+            .as_string()
+            .ok_or_else(|| JsError::new("event type should be a string"))?;
+        let kind = EventKind::from_str(&kind)?;
+        let listener: js_sys::Function = listener
+            .dyn_into()
+            .map_err(|_| JsError::new("listener should be a function"))?;
+        let mut events = self.processor.swarm.subscribe();
+        let (handle, registration) = futures::future::AbortHandle::new_pair();
+        let dispatch = async move {
+            let this = JsValue::null();
+            while let Some(ev) = events.next().await {
+                match event::to_js_event(&ev) {
+                    Ok(Some((k, v))) if k == kind => {
+                        if let Err(e) = listener.call1(&this, &v) {
+                            log::warn!("failed to invoke {} listener: {:?}", kind.as_str(), e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::error!("failed to convert event: {:?}", e),
+                }
+            }
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            futures::future::Abortable::new(dispatch, registration)
+                .await
+                .ok();
+        });
+        Ok(self.listeners.insert(handle))
+    }
+
+    /// remove a listener added by `add_event_listener`, return false if it's not found.
+    pub fn remove_event_listener(&self, id: u32) -> bool {
+        self.listeners.remove(id)
+    }
+
+    /// subscribe events of swarm and dht, callback is invoked with each event.
+    /// ```typescript
+    /// client.on_event((ev: any) => {
+    ///   // ev: { type: 'PeerConnected', data: '0x...' }
+    ///   console.log(ev.type, ev.data)
+    /// })
+    /// ```
+    pub fn on_event(&self, callback: js_sys::Function) {
+        let mut events = self.processor.swarm.subscribe();
+        wasm_bindgen_futures::spawn_local(async move {
+            let this = JsValue::null();
+            while let Some(ev) = events.next().await {
+                match JsValue::from_serde(&ev) {
+                    Ok(v) => {
+                        if let Err(e) = callback.call1(&this, &v) {
+                            log::warn!("failed to invoke event callback: {:?}", e);
+                        }
+                    }
+                    Err(e) => log::error!("failed to serialize event: {}", e),
+                }
+            }
+        });
+    }
 }
 
 #[wasm_bindgen]
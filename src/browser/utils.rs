@@ -1,3 +1,7 @@
+use crate::prelude::js_sys;
+use crate::prelude::wasm_bindgen::JsValue;
+use crate::prelude::wasm_bindgen_futures::JsFuture;
+use crate::prelude::web_sys;
 use crate::prelude::web_sys::RtcIceConnectionState;
 
 pub fn set_panic_hook() {
@@ -11,6 +15,19 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+/// Resolve after `ms` milliseconds, by `setTimeout` of window.
+pub async fn sleep(ms: i32) -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let r = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("window not found"))
+            .and_then(|w| w.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms));
+        if let Err(e) = r {
+            reject.call1(&JsValue::null(), &e).ok();
+        }
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
+
 pub fn from_rtc_ice_connection_state(state: RtcIceConnectionState) -> String {
     match state {
         RtcIceConnectionState::New => "new",
@@ -1,11 +1,27 @@
+//use jsonrpc_core_client::RawClient;
+use futures::stream::select_all;
+use futures::StreamExt;
 use jsonrpc_core::Params;
 use jsonrpc_core::Value;
-//use jsonrpc_core_client::RawClient;
 use serde_json::json;
 
+use crate::crawler::CrawlFormat;
+use crate::crawler::RingMap;
+use crate::jsonrpc::auth::Credential;
 use crate::jsonrpc::method::Method;
+use crate::jsonrpc::method::Topic;
+use crate::jsonrpc::method::SUBSCRIPTION_NOTIFICATION;
+use crate::jsonrpc::response::FetchedValue;
+use crate::jsonrpc::response::NodeInfo;
 use crate::jsonrpc::response::Peer;
+use crate::jsonrpc::response::PendingTransport;
+use crate::jsonrpc::response::PingResult;
+use crate::jsonrpc::response::RoutingTable;
+use crate::jsonrpc::response::StoredValue;
+use crate::jsonrpc::response::SubRingInfo;
 use crate::jsonrpc::response::TransportAndIce;
+use crate::jsonrpc_client::client::SubscribeMessage;
+use crate::jsonrpc_client::client::Subscription;
 use crate::jsonrpc_client::SimpleClient;
 
 #[derive(Clone)]
@@ -20,8 +36,8 @@ pub struct ClientOutput<T> {
 type Output<T> = anyhow::Result<ClientOutput<T>>;
 
 impl Client {
-    pub async fn new(endpoint_url: &str) -> anyhow::Result<Self> {
-        let client = SimpleClient::new_with_url(endpoint_url);
+    pub async fn new(endpoint_url: &str, credential: Option<Credential>) -> anyhow::Result<Self> {
+        let client = SimpleClient::new_with_credential(endpoint_url, credential);
         Ok(Self { client })
     }
 
@@ -159,12 +175,17 @@ impl Client {
             .call_method(Method::ListPendings.as_str(), Params::Array(vec![]))
             .await
             .map_err(|e| anyhow::anyhow!("{}", e))?;
-        let resp: Vec<String> =
+        let resp: Vec<PendingTransport> =
             serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
         let mut display = String::new();
-        for item in resp.iter() {
-            display.push_str(item)
-        }
+        display.push_str("TransportId, Age(s)\n");
+        display.push_str(
+            resp.iter()
+                .map(|item| format!("{}, {}", item.transport_id, item.age))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .as_str(),
+        );
         ClientOutput::ok(display, ())
     }
 
@@ -189,6 +210,277 @@ impl Client {
             .map_err(|e| anyhow::anyhow!("{}", e))?;
         ClientOutput::ok("Done.".into(), ())
     }
+
+    pub async fn store_value(&self, value: &str) -> Output<StoredValue> {
+        let resp = self
+            .client
+            .call_method(
+                Method::StoreValue.as_str(),
+                Params::Array(vec![json!(value)]),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let stored: StoredValue =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        ClientOutput::ok(format!("Successful, did: {}", stored.did), stored)
+    }
+
+    pub async fn fetch_value(&self, did: &str) -> Output<Option<FetchedValue>> {
+        let resp = self
+            .client
+            .call_method(Method::FetchValue.as_str(), Params::Array(vec![json!(did)]))
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let fetched: Option<FetchedValue> =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let display = match &fetched {
+            Some(v) => format!("{}, {}\n{}", v.did, v.kind, v.data.join("\n")),
+            None => "Not found yet, the value will be cached once found.".to_owned(),
+        };
+        ClientOutput::ok(display, fetched)
+    }
+
+    pub async fn create_subring(&self, name: &str) -> Output<StoredValue> {
+        let resp = self
+            .client
+            .call_method(
+                Method::CreateSubRing.as_str(),
+                Params::Array(vec![json!(name)]),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let stored: StoredValue =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        ClientOutput::ok(format!("Successful, did: {}", stored.did), stored)
+    }
+
+    pub async fn join_subring(&self, name: &str) -> Output<()> {
+        self.client
+            .call_method(
+                Method::JoinSubRing.as_str(),
+                Params::Array(vec![json!(name)]),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        ClientOutput::ok("Done.".into(), ())
+    }
+
+    pub async fn list_subrings(&self) -> Output<Vec<SubRingInfo>> {
+        let resp = self
+            .client
+            .call_method(Method::ListSubRings.as_str(), Params::Array(vec![]))
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let subrings: Vec<SubRingInfo> =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let mut display = String::new();
+        display.push_str("Name, Did, Creator, Members\n");
+        display.push_str(
+            subrings
+                .iter()
+                .map(|r| format!("{}, {}, {}, {}", r.name, r.did, r.creator, r.members.len()))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .as_str(),
+        );
+        ClientOutput::ok(display, subrings)
+    }
+
+    pub async fn ping(&self, address: &str) -> Output<Option<PingResult>> {
+        let mut params = serde_json::Map::new();
+        params.insert("address".to_owned(), json!(address));
+        let resp = self
+            .client
+            .call_method(Method::Ping.as_str(), Params::Map(params))
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let result: Option<PingResult> =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let display = match &result {
+            Some(r) if r.responder() == Some(r.target.as_str()) => format!(
+                "Reply from {}: hops={} time={}ms",
+                r.target,
+                r.hops.len().saturating_sub(1),
+                r.rtt_ms
+            ),
+            Some(r) => format!(
+                "Destination {} unreachable, reported by {}.",
+                r.target,
+                r.responder().unwrap_or_default()
+            ),
+            None => "Request timeout.".to_owned(),
+        };
+        ClientOutput::ok(display, result)
+    }
+
+    pub async fn traceroute(&self, did: &str) -> Output<Option<PingResult>> {
+        let mut params = serde_json::Map::new();
+        params.insert("did".to_owned(), json!(did));
+        let resp = self
+            .client
+            .call_method(Method::Traceroute.as_str(), Params::Map(params))
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let result: Option<PingResult> =
+            serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let display = match &result {
+            Some(r) => {
+                let mut lines = vec![format!("Traceroute to {}", r.target)];
+                lines.extend(
+                    r.hops
+                        .iter()
+                        .enumerate()
+                        .map(|(i, h)| format!("{:>3}  {}  {}ms", i, h.did, h.elapsed_ms)),
+                );
+                lines.join("\n")
+            }
+            None => "Request timeout.".to_owned(),
+        };
+        ClientOutput::ok(display, result)
+    }
+
+    /// Crawl ring from `start`, or from the node if it's None.
+    pub async fn crawl(
+        &self,
+        start: Option<&str>,
+        max_nodes: usize,
+        format: CrawlFormat,
+    ) -> Output<RingMap> {
+        let start = match start {
+            Some(start) => start.to_owned(),
+            None => self.inspect().await?.result.routing.id,
+        };
+        let map = RingMap::crawl(&start, max_nodes, |did| {
+            let client = self.client.clone();
+            async move {
+                let mut params = serde_json::Map::new();
+                params.insert("did".to_owned(), json!(did));
+                let resp = client
+                    .call_method(Method::GetRoutingTable.as_str(), Params::Map(params))
+                    .await
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                log::debug!("routing table of {}: {:?}", did, resp);
+                let table: Option<RoutingTable> =
+                    serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+                Ok(table)
+            }
+        })
+        .await;
+        let display = match format {
+            CrawlFormat::Json => serde_json::to_string_pretty(&map)?,
+            CrawlFormat::Dot => map.to_dot(),
+        };
+        ClientOutput::ok(display, map)
+    }
+
+    pub async fn inspect(&self) -> Output<NodeInfo> {
+        let resp = self
+            .client
+            .call_method(Method::NodeInfo.as_str(), Params::Array(vec![]))
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let info: NodeInfo = serde_json::from_value(resp).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let mut display = vec![
+            format!("Version: {}", info.version),
+            format!("Address: {}", info.address),
+            format!(
+                "Session: authorizer {}, session address {}, expires at {}{}",
+                info.session.authorizer,
+                info.session.session_address,
+                info.session
+                    .expires_at
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "never".to_owned()),
+                if info.session.expired {
+                    " (expired)"
+                } else {
+                    ""
+                }
+            ),
+            format!("Did: {}", info.routing.id),
+            format!(
+                "Predecessor: {}",
+                info.routing.predecessor.as_deref().unwrap_or("none")
+            ),
+            format!("Successors: [{}]", info.routing.successors.join(", ")),
+            format!("FixFingerIndex: {}", info.routing.fix_finger_index),
+            "Finger:".to_owned(),
+        ];
+        // merge continuous entries with same did
+        let mut start = 0;
+        let finger = &info.routing.finger;
+        for i in 0..finger.len() {
+            if i + 1 == finger.len() || finger[i + 1] != finger[i] {
+                if let Some(did) = &finger[i] {
+                    display.push(format!("  [{}..{}] {}", start, i, did));
+                }
+                start = i + 1;
+            }
+        }
+        display.push(format!(
+            "Storage: {} vnodes, {} cached",
+            info.storage.storage, info.storage.cache
+        ));
+        display.push("Transports: Address, TransportId, State".to_owned());
+        for t in info.transports.iter() {
+            display.push(format!(
+                "  {}, {}, {}",
+                t.address,
+                t.transport_id,
+                t.state.as_deref().unwrap_or("none")
+            ));
+        }
+        display.push(format!("Pending transports: {}", info.pending_transports));
+
+        ClientOutput::ok(display.join("\n"), info)
+    }
+
+    /// Subscribe topics and print each event until the connection closed.
+    pub async fn listen(&self, topics: &[Topic]) -> Output<()> {
+        let mut streams = vec![];
+        for topic in topics {
+            let stream = self
+                .client
+                .subscribe(SubscribeMessage {
+                    subscription: Subscription {
+                        subscribe: Method::Subscribe.to_string(),
+                        subscribe_params: Params::Array(vec![json!(topic.as_str())]),
+                        notification: SUBSCRIPTION_NOTIFICATION.to_owned(),
+                        unsubscribe: Method::Unsubscribe.to_string(),
+                    },
+                })
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            streams.push(stream);
+        }
+        let mut events = select_all(streams);
+        while let Some(ev) = events.next().await {
+            match ev {
+                Ok(ev) => println!("{}", Self::format_event(&ev)),
+                Err(e) => log::error!("subscription error: {}", e),
+            }
+        }
+        ClientOutput::ok("Connection closed.".into(), ())
+    }
+
+    fn format_event(ev: &Value) -> String {
+        match (ev["type"].as_str(), ev["data"]["data"].as_array()) {
+            (Some("CustomMessage"), Some(data)) => {
+                let data = data
+                    .iter()
+                    .filter_map(|x| x.as_u64().map(|x| x as u8))
+                    .collect::<Vec<_>>();
+                format!(
+                    "CustomMessage from {}: {}",
+                    ev["data"]["origin"].as_str().unwrap_or_default(),
+                    String::from_utf8_lossy(&data)
+                )
+            }
+            (Some(t), _) => format!("{}: {}", t, ev["data"]),
+            _ => ev.to_string(),
+        }
+    }
 }
 
 impl<T> ClientOutput<T> {
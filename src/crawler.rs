@@ -0,0 +1,323 @@
+//! Crawler of ring topology.
+//!
+//! Starting from a did, routing tables are collected by `getRoutingTable`, and nodes in
+//! predecessor, successors and finger of each table are visited in turn. The discovered
+//! ring is checked for broken successor links, predecessor mismatches and gaps, where
+//! a node's successor skips other discovered nodes in identifier space.
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::future::Future;
+
+use clap::ArgEnum;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::jsonrpc::response::RoutingTable;
+
+/// Output format of crawled ring.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[clap(rename_all = "kebab-case")]
+pub enum CrawlFormat {
+    /// [RingMap] as json
+    Json,
+    /// Graphviz DOT
+    Dot,
+}
+
+/// Inconsistency found in crawled ring.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Issue {
+    /// node has no successor, while there are other nodes in ring
+    NoSuccessor { node: String },
+    /// successor of node doesn't report its routing table
+    BrokenSuccessor { node: String, successor: String },
+    /// predecessor of successor is not the node
+    PredecessorMismatch {
+        node: String,
+        successor: String,
+        predecessor: Option<String>,
+    },
+    /// discovered nodes between node and its successor
+    Gap {
+        node: String,
+        successor: String,
+        skipped: Vec<String>,
+    },
+}
+
+/// Ring discovered by crawler.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct RingMap {
+    /// did where crawling started
+    pub start: String,
+    /// routing tables of discovered nodes, by did
+    pub nodes: BTreeMap<String, RoutingTable>,
+    /// dids pointed by other nodes, which don't report routing table
+    pub unreachable: BTreeSet<String>,
+    /// inconsistencies found by [RingMap::check]
+    pub issues: Vec<Issue>,
+}
+
+impl RingMap {
+    /// Crawl ring from `start`, until no more nodes are found or `max_nodes` are visited,
+    /// then check it. `get_routing_table` returns None if the table is not reported,
+    /// a did is taken as unreachable in both that case and when the call fails.
+    pub async fn crawl<F, Fut>(start: &str, max_nodes: usize, mut get_routing_table: F) -> Self
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = anyhow::Result<Option<RoutingTable>>>,
+    {
+        let mut map = Self {
+            start: start.to_owned(),
+            ..Default::default()
+        };
+        let mut visited = BTreeSet::from([start.to_owned()]);
+        let mut queue = VecDeque::from([start.to_owned()]);
+        while let Some(did) = queue.pop_front() {
+            if map.nodes.len() >= max_nodes {
+                break;
+            }
+            let table = match get_routing_table(did.clone()).await {
+                Ok(Some(table)) => table,
+                Ok(None) => {
+                    map.unreachable.insert(did);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("failed to get routing table of {}: {}", did, e);
+                    map.unreachable.insert(did);
+                    continue;
+                }
+            };
+            // the node of did is gone, if another node takes charge of it
+            if table.did != did {
+                map.unreachable.insert(did);
+            }
+            visited.insert(table.did.clone());
+            for next in table
+                .predecessor
+                .iter()
+                .chain(table.successors.iter())
+                .chain(table.finger.iter())
+            {
+                if visited.insert(next.clone()) {
+                    queue.push_back(next.clone());
+                }
+            }
+            map.nodes.insert(table.did.clone(), table);
+        }
+        map.check();
+        map
+    }
+
+    /// Check consistency between each node and its first successor.
+    /// Nodes not crawled, because of `max_nodes`, are not checked.
+    pub fn check(&mut self) {
+        let mut issues = vec![];
+        for (did, table) in self.nodes.iter() {
+            let successor = match table.successors.first() {
+                Some(successor) => successor,
+                None => {
+                    if self.nodes.len() > 1 {
+                        issues.push(Issue::NoSuccessor { node: did.clone() });
+                    }
+                    continue;
+                }
+            };
+            if self.unreachable.contains(successor) {
+                issues.push(Issue::BrokenSuccessor {
+                    node: did.clone(),
+                    successor: successor.clone(),
+                });
+            } else if let Some(succ_table) = self.nodes.get(successor) {
+                if succ_table.predecessor.as_ref() != Some(did) {
+                    issues.push(Issue::PredecessorMismatch {
+                        node: did.clone(),
+                        successor: successor.clone(),
+                        predecessor: succ_table.predecessor.clone(),
+                    });
+                }
+            }
+            let skipped = self.between(did, successor);
+            if !skipped.is_empty() {
+                issues.push(Issue::Gap {
+                    node: did.clone(),
+                    successor: successor.clone(),
+                    skipped,
+                });
+            }
+        }
+        self.issues = issues;
+    }
+
+    /// Discovered nodes in (a, b) clockwise. Dids are fixed-length lowercase hex,
+    /// so they are ordered as strings in the same way as numbers.
+    fn between(&self, a: &str, b: &str) -> Vec<String> {
+        let inside = |did: &&String| {
+            if a < b {
+                did.as_str() > a && did.as_str() < b
+            } else {
+                did.as_str() > a || did.as_str() < b
+            }
+        };
+        self.nodes.keys().filter(inside).cloned().collect()
+    }
+
+    /// Graphviz DOT of ring. Successor links are solid, and the first one is bold,
+    /// finger links are dotted, unreachable nodes and broken links are red.
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph ring {".to_owned()];
+        for issue in self.issues.iter() {
+            lines.push(format!(
+                "  // issue: {}",
+                serde_json::to_string(issue).unwrap_or_default()
+            ));
+        }
+        for did in self.nodes.keys() {
+            let style = if *did == self.start {
+                " [style=filled]"
+            } else {
+                ""
+            };
+            lines.push(format!("  \"{}\"{};", did, style));
+        }
+        for did in self.unreachable.iter() {
+            lines.push(format!("  \"{}\" [color=red, style=dashed];", did));
+        }
+        for (did, table) in self.nodes.iter() {
+            for (i, successor) in table.successors.iter().enumerate() {
+                let mut attrs = vec![];
+                if i == 0 {
+                    attrs.push("penwidth=2");
+                }
+                if self.unreachable.contains(successor) {
+                    attrs.push("color=red");
+                }
+                lines.push(format!(
+                    "  \"{}\" -> \"{}\" [{}];",
+                    did,
+                    successor,
+                    attrs.join(", ")
+                ));
+            }
+            for finger in table.finger.iter() {
+                if !table.successors.contains(finger) {
+                    lines.push(format!("  \"{}\" -> \"{}\" [style=dotted];", did, finger));
+                }
+            }
+        }
+        lines.push("}".to_owned());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn did(n: u8) -> String {
+        format!("0x{:040x}", n)
+    }
+
+    fn table(n: u8, predecessor: Option<u8>, successors: &[u8], finger: &[u8]) -> RoutingTable {
+        RoutingTable {
+            did: did(n),
+            predecessor: predecessor.map(did),
+            successors: successors.iter().map(|n| did(*n)).collect(),
+            finger: finger.iter().map(|n| did(*n)).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crawl_and_check_ring() {
+        // 0x10 -> 0x20 -> 0x30 -> 0x40 -> 0x10, but 0x20 points to 0x40,
+        // and 0x40 points to 0x50 which is gone, answered by 0x10.
+        let tables = HashMap::from([
+            (did(0x10), table(0x10, Some(0x40), &[0x20], &[0x20, 0x30])),
+            (did(0x20), table(0x20, Some(0x10), &[0x40], &[0x40])),
+            (did(0x30), table(0x30, Some(0x20), &[0x40], &[0x40, 0x10])),
+            (did(0x40), table(0x40, Some(0x30), &[0x50], &[0x50])),
+            (did(0x50), table(0x10, Some(0x40), &[0x20], &[0x20, 0x30])),
+        ]);
+        let map = RingMap::crawl(&did(0x10), 16, |d| {
+            let t = tables.get(&d).cloned();
+            async move { Ok(t) }
+        })
+        .await;
+        assert_eq!(map.nodes.len(), 4);
+        assert_eq!(map.unreachable, BTreeSet::from([did(0x50)]));
+        assert_eq!(map.issues, vec![
+            Issue::PredecessorMismatch {
+                node: did(0x20),
+                successor: did(0x40),
+                predecessor: Some(did(0x30)),
+            },
+            Issue::Gap {
+                node: did(0x20),
+                successor: did(0x40),
+                skipped: vec![did(0x30)],
+            },
+            Issue::BrokenSuccessor {
+                node: did(0x40),
+                successor: did(0x50),
+            },
+        ]);
+
+        let dot = map.to_dot();
+        assert!(dot.starts_with("digraph ring {"));
+        assert!(dot.contains(&format!("\"{}\" [color=red, style=dashed];", did(0x50))));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [penwidth=2, color=red];",
+            did(0x40),
+            did(0x50)
+        )));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [style=dotted];",
+            did(0x10),
+            did(0x30)
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_stops_at_max_nodes() {
+        let map = RingMap::crawl(&did(1), 3, |d| async move {
+            let n = u8::from_str_radix(&d[2..], 16).unwrap();
+            Ok(Some(table(n, Some(n.wrapping_sub(1)), &[n + 1], &[
+                n + 1,
+                n + 2,
+            ])))
+        })
+        .await;
+        assert_eq!(map.nodes.len(), 3);
+        assert!(map.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_continues_after_failure() {
+        // 0x10 -> 0x20 -> 0x30 -> 0x10, getting table of 0x20 fails
+        let tables = HashMap::from([
+            (did(0x10), table(0x10, Some(0x30), &[0x20], &[0x20, 0x30])),
+            (did(0x30), table(0x30, Some(0x20), &[0x10], &[0x10])),
+        ]);
+        let map = RingMap::crawl(&did(0x10), 16, |d| {
+            let t = tables.get(&d).cloned();
+            async move {
+                match t {
+                    Some(t) => Ok(Some(t)),
+                    None => Err(anyhow::anyhow!("connection refused")),
+                }
+            }
+        })
+        .await;
+        assert_eq!(map.nodes.len(), 2);
+        assert_eq!(map.unreachable, BTreeSet::from([did(0x20)]));
+        assert_eq!(map.issues, vec![Issue::BrokenSuccessor {
+            node: did(0x10),
+            successor: did(0x20),
+        }]);
+    }
+}
@@ -44,6 +44,48 @@ pub enum Error {
     SendMessage(rings_core::err::Error),
     #[error("Build message body error: {0}")]
     MessagePayload(rings_core::err::Error),
+    #[error("Invalid topic.")]
+    InvalidTopic,
+    #[error("Subscription not found.")]
+    SubscriptionNotFound,
+    #[error("Invalid did.")]
+    InvalidDid,
+    #[error("Store value error: {0}")]
+    StoreValue(rings_core::err::Error),
+    #[error("Fetch value error: {0}")]
+    FetchValue(rings_core::err::Error),
+    #[error("SubRing operation error: {0}")]
+    SubRing(rings_core::err::Error),
+    #[error("Unauthorized.")]
+    Unauthorized,
+    #[error("Permission denied.")]
+    PermissionDenied,
+    #[error("Invalid permission, should be `read` or `write`.")]
+    InvalidPermission,
+    #[error("Read config file failed: {0}.")]
+    ConfigFile(String),
+    #[error("Invalid config: {0}.")]
+    InvalidConfig(String),
+    #[error("Storage error: {0}")]
+    Storage(rings_core::err::Error),
+    #[error("Keystore error: {0}.")]
+    Keystore(String),
+    #[error("Invalid bootstrap peer {0}, should be http url or address.")]
+    InvalidBootstrapPeer(String),
+    #[error("Timeout waiting for bootstrap peer connected.")]
+    BootstrapTimeout,
+    #[error("Failed to connect any bootstrap peer.")]
+    BootstrapFailed,
+    #[error("Signaling request failed: {0}.")]
+    Signaling(String),
+    #[error("Request TURN credential error: {0}")]
+    TurnCredential(rings_core::err::Error),
+    #[error("Registry error: {0}.")]
+    Registry(String),
+    #[error("Ping error: {0}")]
+    Ping(rings_core::err::Error),
+    #[error("Get routing table error: {0}")]
+    RoutingTable(rings_core::err::Error),
 }
 
 impl Error {
@@ -69,6 +111,27 @@ impl Error {
             Error::ConnectError(_) => 17,
             Error::SendMessage(_) => 18,
             Error::MessagePayload(_) => 19,
+            Error::InvalidTopic => 20,
+            Error::SubscriptionNotFound => 21,
+            Error::InvalidDid => 22,
+            Error::StoreValue(_) => 23,
+            Error::FetchValue(_) => 24,
+            Error::SubRing(_) => 25,
+            Error::Unauthorized => 26,
+            Error::PermissionDenied => 27,
+            Error::InvalidPermission => 28,
+            Error::ConfigFile(_) => 29,
+            Error::InvalidConfig(_) => 30,
+            Error::Storage(_) => 31,
+            Error::Keystore(_) => 32,
+            Error::InvalidBootstrapPeer(_) => 33,
+            Error::BootstrapTimeout => 34,
+            Error::BootstrapFailed => 35,
+            Error::Signaling(_) => 36,
+            Error::TurnCredential(_) => 37,
+            Error::Registry(_) => 38,
+            Error::Ping(_) => 39,
+            Error::RoutingTable(_) => 40,
         };
         -32000 - code
     }
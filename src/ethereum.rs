@@ -1,7 +1,38 @@
+//! Connection to Ethereum, and the registry contract of nodes on it.
+//!
+//! The registry is an optional contract, which resolves names of nodes like ENS,
+//! and maps address of a node to its record:
+//!
+//! ```solidity
+//! interface RingsRegistry {
+//!     // `node` is namehash of name, as in ENS
+//!     function addr(bytes32 node) external view returns (address);
+//!     function record(address node) external view returns (string[] bootstrap, bytes sessionPubkey);
+//! }
+//! ```
+//!
+//! `sessionPubkey` is public key of session key the node signs handshakes with.
+//! A peer reached by the record is trusted only if its session is authorized by
+//! the registered address, and its handshake is signed by that session key.
+//!
+//! Lookups are done by `eth_call`, and cached for a while, including misses.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
 use anyhow::anyhow;
 use anyhow::Result;
 
+use crate::config::Config;
+use crate::error::Error;
+use crate::prelude::rings_core::ecc::PublicKey;
 use crate::prelude::rings_core::prelude::web3;
+use crate::prelude::rings_core::prelude::web3::ethabi;
+use crate::prelude::rings_core::prelude::web3::ethabi::Token;
+use crate::prelude::rings_core::prelude::web3::types::Address;
+use crate::prelude::rings_core::prelude::web3::types::Bytes;
+use crate::prelude::rings_core::prelude::web3::types::CallRequest;
 
 pub type Transport = web3::transports::Either<web3::transports::WebSocket, web3::transports::Http>;
 
@@ -16,3 +47,310 @@ pub async fn link_web3(endpoint: &str) -> Result<web3::Web3<Transport>> {
         Err(anyhow!("Failed to parse eth_endpoint {:?}", endpoint))
     }
 }
+
+const REGISTRY_ABI: &str = r#"[
+  {
+    "type": "function",
+    "name": "addr",
+    "stateMutability": "view",
+    "inputs": [{ "name": "node", "type": "bytes32" }],
+    "outputs": [{ "name": "", "type": "address" }]
+  },
+  {
+    "type": "function",
+    "name": "record",
+    "stateMutability": "view",
+    "inputs": [{ "name": "node", "type": "address" }],
+    "outputs": [
+      { "name": "bootstrap", "type": "string[]" },
+      { "name": "sessionPubkey", "type": "bytes" }
+    ]
+  }
+]"#;
+
+/// Record of a node in registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeRecord {
+    /// jsonrpc urls of the node, or of nodes which can reach it
+    pub bootstrap: Vec<String>,
+    /// public key of session key of the node, compressed or not, empty if it's not registered
+    pub session_pubkey: Vec<u8>,
+}
+
+impl NodeRecord {
+    /// Check public key of session key of a peer against the record,
+    /// any key matches an empty one.
+    pub fn matches_session_pubkey(&self, pubkey: &PublicKey) -> bool {
+        match self.session_pubkey.len() {
+            0 => true,
+            33 => self.session_pubkey == pubkey.serialize_compressed(),
+            65 => self.session_pubkey == pubkey.serialize(),
+            _ => false,
+        }
+    }
+}
+
+struct Cached<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+/// Lookups of registry contract, with cache.
+pub struct Registry {
+    web3: web3::Web3<Transport>,
+    contract: Address,
+    abi: ethabi::Contract,
+    cache_ttl: Duration,
+    names: Mutex<HashMap<String, Cached<Option<Address>>>>,
+    records: Mutex<HashMap<Address, Cached<Option<NodeRecord>>>>,
+}
+
+impl Registry {
+    /// Create a Registry of `contract`, results are cached for `cache_ttl`.
+    pub fn new(web3: web3::Web3<Transport>, contract: Address, cache_ttl: Duration) -> Self {
+        Self {
+            web3,
+            contract,
+            abi: ethabi::Contract::load(REGISTRY_ABI.as_bytes()).unwrap(),
+            cache_ttl,
+            names: Mutex::new(HashMap::new()),
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registry on `eth_endpoint`, None if `registry.contract` is not set.
+    pub async fn new_with_config(config: &Config) -> Result<Option<Self>> {
+        let contract = match config.registry.contract()? {
+            Some(contract) => contract,
+            None => return Ok(None),
+        };
+        let web3 = link_web3(&config.eth_endpoint).await?;
+        Ok(Some(Self::new(
+            web3,
+            contract,
+            Duration::from_secs(config.registry.cache_ttl),
+        )))
+    }
+
+    /// Resolve address of a name, None if it's not registered.
+    pub async fn resolve_name(&self, name: &str) -> crate::error::Result<Option<Address>> {
+        if let Some(address) = cached(&self.names, &name.to_owned()) {
+            return Ok(address);
+        }
+        let node = web3::signing::namehash(name);
+        let tokens = self
+            .call("addr", &[Token::FixedBytes(node.to_vec())])
+            .await?;
+        let address = match tokens.as_slice() {
+            [Token::Address(a)] if a.is_zero() => None,
+            [Token::Address(a)] => Some(*a),
+            _ => return Err(Error::Registry("invalid output of addr".to_owned())),
+        };
+        self.cache(&self.names, name.to_owned(), address);
+        Ok(address)
+    }
+
+    /// Look up record of a node, None if it's not registered.
+    pub async fn lookup(&self, address: &Address) -> crate::error::Result<Option<NodeRecord>> {
+        if let Some(record) = cached(&self.records, address) {
+            return Ok(record);
+        }
+        let tokens = self.call("record", &[Token::Address(*address)]).await?;
+        let record = match tokens.as_slice() {
+            [Token::Array(urls), Token::Bytes(pubkey)] => {
+                let bootstrap = urls
+                    .iter()
+                    .filter_map(|t| t.clone().into_string())
+                    .collect::<Vec<_>>();
+                (!bootstrap.is_empty()).then(|| NodeRecord {
+                    bootstrap,
+                    session_pubkey: pubkey.clone(),
+                })
+            }
+            _ => return Err(Error::Registry("invalid output of record".to_owned())),
+        };
+        self.cache(&self.records, *address, record.clone());
+        Ok(record)
+    }
+
+    async fn call(&self, function: &str, input: &[Token]) -> crate::error::Result<Vec<Token>> {
+        let function = self
+            .abi
+            .function(function)
+            .map_err(|e| Error::Registry(e.to_string()))?;
+        let data = function
+            .encode_input(input)
+            .map_err(|e| Error::Registry(e.to_string()))?;
+        let req = CallRequest {
+            to: Some(self.contract),
+            data: Some(Bytes(data)),
+            ..Default::default()
+        };
+        let output = self
+            .web3
+            .eth()
+            .call(req, None)
+            .await
+            .map_err(|e| Error::Registry(e.to_string()))?;
+        function
+            .decode_output(&output.0)
+            .map_err(|e| Error::Registry(e.to_string()))
+    }
+
+    fn cache<K, V>(&self, cache: &Mutex<HashMap<K, Cached<V>>>, key: K, value: V)
+    where K: std::hash::Hash + Eq {
+        let now = Instant::now();
+        let mut cache = cache.lock().unwrap();
+        cache.retain(|_, c| c.expires_at > now);
+        cache.insert(key, Cached {
+            value,
+            expires_at: now + self.cache_ttl,
+        });
+    }
+}
+
+fn cached<K, V>(cache: &Mutex<HashMap<K, Cached<V>>>, key: &K) -> Option<V>
+where
+    K: std::hash::Hash + Eq,
+    V: Clone,
+{
+    let cache = cache.lock().unwrap();
+    cache
+        .get(key)
+        .filter(|c| c.expires_at > Instant::now())
+        .map(|c| c.value.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use axum::routing::post;
+    use axum::Extension;
+    use axum::Json;
+    use axum::Router;
+    use serde_json::json;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::prelude::SecretKey;
+
+    /// Registry contract served over jsonrpc, counting `eth_call` requests.
+    struct MockRegistry {
+        abi: ethabi::Contract,
+        names: HashMap<[u8; 32], Address>,
+        records: HashMap<Address, NodeRecord>,
+        calls: AtomicUsize,
+    }
+
+    impl MockRegistry {
+        fn eth_call(&self, data: &[u8]) -> Vec<u8> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let (selector, input) = data.split_at(4);
+            let addr = self.abi.function("addr").unwrap();
+            let record = self.abi.function("record").unwrap();
+            if selector == addr.short_signature() {
+                let node = addr.decode_input(input).unwrap()[0]
+                    .clone()
+                    .into_fixed_bytes()
+                    .unwrap();
+                let address = self.names.get(node.as_slice()).cloned().unwrap_or_default();
+                ethabi::encode(&[Token::Address(address)])
+            } else {
+                let address = record.decode_input(input).unwrap()[0]
+                    .clone()
+                    .into_address()
+                    .unwrap();
+                let r = self.records.get(&address).cloned().unwrap_or(NodeRecord {
+                    bootstrap: vec![],
+                    session_pubkey: vec![],
+                });
+                ethabi::encode(&[
+                    Token::Array(r.bootstrap.into_iter().map(Token::String).collect()),
+                    Token::Bytes(r.session_pubkey),
+                ])
+            }
+        }
+    }
+
+    async fn handle(
+        Extension(mock): Extension<Arc<MockRegistry>>,
+        Json(req): Json<Value>,
+    ) -> Json<Value> {
+        assert_eq!(req["method"], "eth_call");
+        let data = req["params"][0]["data"].as_str().unwrap();
+        let output = mock.eth_call(&hex::decode(&data[2..]).unwrap());
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": req["id"],
+            "result": format!("0x{}", hex::encode(output)),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_registry_lookup() {
+        let key = SecretKey::random();
+        let session_key = SecretKey::random();
+        let record = NodeRecord {
+            bootstrap: vec!["http://10.0.0.1:50000".to_owned()],
+            session_pubkey: session_key.pubkey().serialize_compressed().to_vec(),
+        };
+        let mock = Arc::new(MockRegistry {
+            abi: ethabi::Contract::load(REGISTRY_ABI.as_bytes()).unwrap(),
+            names: HashMap::from([(web3::signing::namehash("alice.rings"), key.address())]),
+            records: HashMap::from([(key.address(), record.clone())]),
+            calls: AtomicUsize::new(0),
+        });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/", post(handle))
+            .layer(Extension(mock.clone()));
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>()),
+        );
+
+        let registry = Registry::new(
+            link_web3(&endpoint).await.unwrap(),
+            Address::from_low_u64_be(1),
+            Duration::from_secs(60),
+        );
+        assert_eq!(
+            registry.resolve_name("alice.rings").await.unwrap(),
+            Some(key.address())
+        );
+        assert_eq!(registry.resolve_name("bob.rings").await.unwrap(), None);
+        assert_eq!(
+            registry.lookup(&key.address()).await.unwrap(),
+            Some(record.clone())
+        );
+        let unknown = SecretKey::random().address();
+        assert_eq!(registry.lookup(&unknown).await.unwrap(), None);
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 4);
+
+        // hits and misses are both cached
+        registry.resolve_name("alice.rings").await.unwrap();
+        registry.resolve_name("bob.rings").await.unwrap();
+        registry.lookup(&key.address()).await.unwrap();
+        registry.lookup(&unknown).await.unwrap();
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 4);
+
+        assert!(record.matches_session_pubkey(&session_key.pubkey()));
+        assert!(!record.matches_session_pubkey(&key.pubkey()));
+        let record = NodeRecord {
+            session_pubkey: session_key.pubkey().serialize().to_vec(),
+            ..record
+        };
+        assert!(record.matches_session_pubkey(&session_key.pubkey()));
+        let record = NodeRecord {
+            session_pubkey: vec![],
+            ..record
+        };
+        assert!(record.matches_session_pubkey(&key.pubkey()));
+    }
+}
@@ -0,0 +1,342 @@
+#![warn(missing_docs)]
+//! Authentication and permissions of jsonrpc requests.
+//!
+//! A request can be authorized with either of:
+//! * a static token, sent as `Authorization: Bearer <token>`;
+//! * a signature of an allow-listed address, sent with headers `X-Rings-Address`,
+//!   `X-Rings-Timestamp` and `X-Rings-Signature`. The signature is made by
+//!   [SecretKey::sign] on `"{timestamp}\n{body}"` and encoded as hex.
+//!   Each signed request is accepted only once, so the same body can't be sent
+//!   twice by a signer within one millisecond.
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use http::header::HeaderMap;
+use http::header::AUTHORIZATION;
+use subtle::ConstantTimeEq;
+
+use super::method::Method;
+use crate::error::Error;
+use crate::error::Result;
+use crate::prelude::rings_core::ecc;
+use crate::prelude::rings_core::prelude::web3::signing::keccak256;
+use crate::prelude::rings_core::prelude::web3::types::Address;
+use crate::prelude::rings_core::utils;
+use crate::prelude::SecretKey;
+
+/// Header of signer's address.
+pub const ADDRESS_HEADER: &str = "x-rings-address";
+/// Header of signing time in epoch ms.
+pub const TIMESTAMP_HEADER: &str = "x-rings-timestamp";
+/// Header of hex encoded signature.
+pub const SIGNATURE_HEADER: &str = "x-rings-signature";
+
+/// Signed requests older than this are rejected, newer ones are remembered to reject replay.
+const SIGNATURE_TTL_MS: u128 = 5 * 60 * 1000;
+
+/// Permission required by a method, `Write` implies `Read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    /// Query state of node only
+    Read,
+    /// Change state of node, or act as node's identity
+    Write,
+}
+
+impl FromStr for Permission {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            _ => Err(Error::InvalidPermission),
+        }
+    }
+}
+
+/// An allow-listed signer, parsed from `<address>` or `<address>:<read|write>`.
+/// Permission defaults to `write` if omitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedAddress {
+    /// address of signer
+    pub address: Address,
+    /// granted permission
+    pub permission: Permission,
+}
+
+impl FromStr for AllowedAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, permission) = match s.split_once(':') {
+            Some((address, permission)) => (address, permission.parse()?),
+            None => (s, Permission::Write),
+        };
+        let address = Address::from_str(address).map_err(|_| Error::InvalidAddress)?;
+        Ok(Self {
+            address,
+            permission,
+        })
+    }
+}
+
+/// Authentication config of jsonrpc server.
+/// With neither token nor allowed addresses, all requests are accepted.
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// static token with write permission
+    pub token: Option<String>,
+    /// signers allowed to call methods
+    pub allowed: Vec<AllowedAddress>,
+    /// accepted signed requests within [SIGNATURE_TTL_MS], with their timestamps
+    seen: Arc<Mutex<HashMap<SignedRequest, u128>>>,
+}
+
+/// Signer and hash of signed message of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SignedRequest {
+    address: Address,
+    hash: [u8; 32],
+}
+
+impl AuthConfig {
+    /// Create a new AuthConfig
+    pub fn new(token: Option<String>, allowed: Vec<AllowedAddress>) -> Self {
+        Self {
+            token,
+            allowed,
+            seen: Default::default(),
+        }
+    }
+
+    /// Return true if requests should be authenticated.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || !self.allowed.is_empty()
+    }
+
+    /// Check credential in `headers` has `required` permission for `body`.
+    pub fn authorize(&self, headers: &HeaderMap, body: &str, required: Permission) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let (granted, signed) = self.authenticate(headers, body)?;
+        if granted < required {
+            return Err(Error::PermissionDenied);
+        }
+        match signed {
+            Some((request, timestamp)) => self.accept_once(request, timestamp),
+            None => Ok(()),
+        }
+    }
+
+    /// Return the permission granted to credential in `headers`,
+    /// with the signed request and its timestamp if it's signed.
+    fn authenticate(
+        &self,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Result<(Permission, Option<(SignedRequest, u128)>)> {
+        if let Some(token) =
+            header_str(headers, AUTHORIZATION.as_str()).and_then(|v| v.strip_prefix("Bearer "))
+        {
+            return match &self.token {
+                Some(t) if bool::from(t.as_bytes().ct_eq(token.as_bytes())) => {
+                    Ok((Permission::Write, None))
+                }
+                _ => Err(Error::Unauthorized),
+            };
+        }
+        let address = header_str(headers, ADDRESS_HEADER).ok_or(Error::Unauthorized)?;
+        let timestamp = header_str(headers, TIMESTAMP_HEADER).ok_or(Error::Unauthorized)?;
+        let signature = header_str(headers, SIGNATURE_HEADER).ok_or(Error::Unauthorized)?;
+
+        let address = Address::from_str(address).map_err(|_| Error::Unauthorized)?;
+        let allowed = self
+            .allowed
+            .iter()
+            .find(|a| a.address == address)
+            .ok_or(Error::Unauthorized)?;
+        let ts = timestamp.parse::<u128>().map_err(|_| Error::Unauthorized)?;
+        let now = utils::get_epoch_ms();
+        if now.saturating_sub(ts) > SIGNATURE_TTL_MS || ts.saturating_sub(now) > SIGNATURE_TTL_MS {
+            return Err(Error::Unauthorized);
+        }
+        let sig = hex::decode(signature).map_err(|_| Error::Unauthorized)?;
+        let message = signing_message(timestamp, body);
+        let pubkey = ecc::recover(&message, sig).map_err(|_| Error::Unauthorized)?;
+        if pubkey.address() != address {
+            return Err(Error::Unauthorized);
+        }
+        let request = SignedRequest {
+            address,
+            hash: keccak256(message.as_bytes()),
+        };
+        Ok((allowed.permission, Some((request, ts))))
+    }
+
+    /// Remember a signed request until it expires, reject it if it's seen already.
+    /// Requests are keyed by signed message instead of signature, which is malleable.
+    fn accept_once(&self, request: SignedRequest, timestamp: u128) -> Result<()> {
+        let now = utils::get_epoch_ms();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, ts| now.saturating_sub(*ts) <= SIGNATURE_TTL_MS);
+        let replayed = seen.insert(request, timestamp).is_some();
+        if replayed {
+            Err(Error::Unauthorized)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Permission required by a jsonrpc request body, which can be a single call or a batch.
+/// Unknown methods require `Write`.
+pub fn required_permission(body: &str) -> Permission {
+    let value: serde_json::Value = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return Permission::Read,
+    };
+    let calls = match &value {
+        serde_json::Value::Array(calls) => calls.iter().collect::<Vec<_>>(),
+        v => vec![v],
+    };
+    calls
+        .into_iter()
+        .map(|call| {
+            call.get("method")
+                .and_then(|m| m.as_str())
+                .and_then(|m| Method::try_from(m).ok())
+                .map(|m| m.permission())
+                .unwrap_or(Permission::Write)
+        })
+        .max()
+        .unwrap_or(Permission::Read)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn signing_message(timestamp: &str, body: &str) -> String {
+    format!("{}\n{}", timestamp, body)
+}
+
+/// Credential sent by client.
+#[derive(Clone)]
+pub enum Credential {
+    /// static token
+    Token(String),
+    /// key of an allow-listed address, which signs each request
+    Key(SecretKey),
+}
+
+impl Credential {
+    /// Headers to authenticate a request with `body`.
+    pub fn headers(&self, body: &str) -> Vec<(&'static str, String)> {
+        match self {
+            Credential::Token(token) => {
+                vec![(AUTHORIZATION.as_str(), format!("Bearer {}", token))]
+            }
+            Credential::Key(key) => {
+                let timestamp = utils::get_epoch_ms().to_string();
+                let sig = key.sign(&signing_message(&timestamp, body));
+                vec![
+                    (ADDRESS_HEADER, format!("{:?}", key.address())),
+                    (TIMESTAMP_HEADER, timestamp),
+                    (SIGNATURE_HEADER, hex::encode(sig)),
+                ]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::header::HeaderName;
+    use http::header::HeaderValue;
+
+    use super::*;
+
+    fn to_header_map(credential: &Credential, body: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in credential.headers(body) {
+            headers.insert(
+                HeaderName::from_static(k),
+                HeaderValue::from_str(&v).unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn test_required_permission() {
+        let list = r#"{"jsonrpc":"2.0","id":1,"method":"listPeers","params":[]}"#;
+        let send = r#"{"jsonrpc":"2.0","id":1,"method":"sendTo","params":[]}"#;
+        assert_eq!(required_permission(list), Permission::Read);
+        assert_eq!(required_permission(send), Permission::Write);
+        assert_eq!(
+            required_permission(&format!("[{},{}]", list, send)),
+            Permission::Write
+        );
+        assert_eq!(
+            required_permission(r#"{"method":"unknown"}"#),
+            Permission::Write
+        );
+    }
+
+    #[test]
+    fn test_authorize() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"listPeers","params":[]}"#;
+        let admin = SecretKey::random();
+        let reader = SecretKey::random();
+        let stranger = SecretKey::random();
+        let auth = AuthConfig::new(Some("secret".to_owned()), vec![
+            format!("{:?}", admin.address()).parse().unwrap(),
+            format!("{:?}:read", reader.address()).parse().unwrap(),
+        ]);
+        assert!(!AuthConfig::default().is_enabled());
+        assert!(AuthConfig::default()
+            .authorize(&HeaderMap::new(), body, Permission::Write)
+            .is_ok());
+
+        let headers = HeaderMap::new();
+        assert!(matches!(
+            auth.authorize(&headers, body, Permission::Read),
+            Err(Error::Unauthorized)
+        ));
+
+        let headers = to_header_map(&Credential::Token("secret".to_owned()), body);
+        assert!(auth.authorize(&headers, body, Permission::Write).is_ok());
+        let headers = to_header_map(&Credential::Token("wrong".to_owned()), body);
+        assert!(auth.authorize(&headers, body, Permission::Read).is_err());
+
+        let headers = to_header_map(&Credential::Key(admin), body);
+        assert!(auth.authorize(&headers, body, Permission::Write).is_ok());
+        // signature is bound to body
+        assert!(auth.authorize(&headers, "{}", Permission::Read).is_err());
+        // signed request is accepted only once
+        assert!(matches!(
+            auth.authorize(&headers, body, Permission::Write),
+            Err(Error::Unauthorized)
+        ));
+        let other = r#"{"jsonrpc":"2.0","id":2,"method":"listPeers","params":[]}"#;
+        let headers = to_header_map(&Credential::Key(admin), other);
+        assert!(auth.authorize(&headers, other, Permission::Write).is_ok());
+
+        let headers = to_header_map(&Credential::Key(reader), body);
+        assert!(auth.authorize(&headers, body, Permission::Read).is_ok());
+        assert!(matches!(
+            auth.authorize(&headers, body, Permission::Write),
+            Err(Error::PermissionDenied)
+        ));
+
+        let headers = to_header_map(&Credential::Key(stranger), body);
+        assert!(matches!(
+            auth.authorize(&headers, body, Permission::Read),
+            Err(Error::Unauthorized)
+        ));
+    }
+}
@@ -1,4 +1,6 @@
 #![warn(missing_docs)]
+//! Methods and topics of jsonrpc server.
+use super::auth::Permission;
 use crate::error::Error;
 use crate::error::Result;
 
@@ -25,6 +27,30 @@ pub enum Method {
     ListPendings,
     /// Close pending connect
     ClosePendingTransport,
+    /// Subscribe a topic, only available on websocket
+    Subscribe,
+    /// Cancel a subscription, only available on websocket
+    Unsubscribe,
+    /// Store a value on DHT
+    StoreValue,
+    /// Fetch a value from DHT with it's did
+    FetchValue,
+    /// Create a subring
+    CreateSubRing,
+    /// Join a subring
+    JoinSubRing,
+    /// List subrings stored on local node
+    ListSubRings,
+    /// Request a credential of TURN server from a connected peer
+    RequestTurnCredential,
+    /// Inspect routing state, session, transports and storage of node
+    NodeInfo,
+    /// Ping a node by address through ring, return round trip time
+    Ping,
+    /// Route a ping to a did through ring, return every hop passed
+    Traceroute,
+    /// Get routing table of node responsible for a did
+    GetRoutingTable,
 }
 
 impl Method {
@@ -41,6 +67,37 @@ impl Method {
             Method::AcceptAnswer => "acceptAnswer",
             Method::ListPendings => "listPendings",
             Method::ClosePendingTransport => "closePendingTransport",
+            Method::Subscribe => "subscribe",
+            Method::Unsubscribe => "unsubscribe",
+            Method::StoreValue => "storeValue",
+            Method::FetchValue => "fetchValue",
+            Method::CreateSubRing => "createSubRing",
+            Method::JoinSubRing => "joinSubRing",
+            Method::ListSubRings => "listSubRings",
+            Method::RequestTurnCredential => "requestTurnCredential",
+            Method::NodeInfo => "nodeInfo",
+            Method::Ping => "ping",
+            Method::Traceroute => "traceroute",
+            Method::GetRoutingTable => "getRoutingTable",
+        }
+    }
+}
+
+impl Method {
+    /// Permission required to call method
+    pub fn permission(&self) -> Permission {
+        match self {
+            Method::ListPeers
+            | Method::ListPendings
+            | Method::Subscribe
+            | Method::Unsubscribe
+            | Method::FetchValue
+            | Method::ListSubRings
+            | Method::NodeInfo
+            | Method::Ping
+            | Method::Traceroute
+            | Method::GetRoutingTable => Permission::Read,
+            _ => Permission::Write,
         }
     }
 }
@@ -66,7 +123,64 @@ impl TryFrom<&str> for Method {
             "acceptAnswer" => Self::AcceptAnswer,
             "listPendings" => Self::ListPendings,
             "closePendingTransport" => Self::ClosePendingTransport,
+            "subscribe" => Self::Subscribe,
+            "unsubscribe" => Self::Unsubscribe,
+            "storeValue" => Self::StoreValue,
+            "fetchValue" => Self::FetchValue,
+            "createSubRing" => Self::CreateSubRing,
+            "joinSubRing" => Self::JoinSubRing,
+            "listSubRings" => Self::ListSubRings,
+            "requestTurnCredential" => Self::RequestTurnCredential,
+            "nodeInfo" => Self::NodeInfo,
+            "ping" => Self::Ping,
+            "traceroute" => Self::Traceroute,
+            "getRoutingTable" => Self::GetRoutingTable,
             _ => return Err(Error::InvalidMethod),
         })
     }
 }
+
+/// Name of notification method of subscriptions.
+pub const SUBSCRIPTION_NOTIFICATION: &str = "subscription";
+
+/// topics can be subscribed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    /// Custom messages sent to this node
+    CustomMessage,
+    /// Peer connected or disconnected, successor or predecessor changed,
+    /// TURN credential issued by peer
+    PeerEvent,
+    /// VNode stored or fetched, subring joined
+    DhtEvent,
+}
+
+impl Topic {
+    /// Return topic's name as `&str`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Topic::CustomMessage => "customMessage",
+            Topic::PeerEvent => "peerEvent",
+            Topic::DhtEvent => "dhtEvent",
+        }
+    }
+}
+
+impl ToString for Topic {
+    fn to_string(&self) -> String {
+        self.as_str().to_owned()
+    }
+}
+
+impl TryFrom<&str> for Topic {
+    type Error = crate::error::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        Ok(match value {
+            "customMessage" => Self::CustomMessage,
+            "peerEvent" => Self::PeerEvent,
+            "dhtEvent" => Self::DhtEvent,
+            _ => return Err(Error::InvalidTopic),
+        })
+    }
+}
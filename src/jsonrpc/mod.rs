@@ -1,8 +1,13 @@
 ///! jsonrpc-server of rings-node
 ///! [JSON-RPC]: https://www.jsonrpc.org/specification
+pub mod auth;
 pub mod method;
+#[cfg(feature = "client")]
+mod pubsub;
 pub mod response;
 #[cfg(feature = "client")]
 mod server;
 #[cfg(feature = "client")]
+pub(crate) use self::pubsub::build_pubsub_handler;
+#[cfg(feature = "client")]
 pub(crate) use self::server::build_handler;
@@ -0,0 +1,193 @@
+#![warn(missing_docs)]
+//! Publish [RingEvent] of swarm to subscribers over websocket.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use futures::StreamExt;
+use jsonrpc_core::Error;
+use jsonrpc_core::Params;
+use jsonrpc_core::Value;
+use jsonrpc_pubsub::PubSubHandler;
+use jsonrpc_pubsub::Session;
+use jsonrpc_pubsub::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+use tokio::task::JoinHandle;
+
+use super::method::Method;
+use super::method::Topic;
+use super::method::SUBSCRIPTION_NOTIFICATION;
+use crate::error::Error as ServerError;
+use crate::prelude::rings_core::event::RingEvent;
+use crate::prelude::rings_core::swarm::Swarm;
+use crate::prelude::uuid;
+
+type Subscriptions = Arc<Mutex<HashMap<SubscriptionId, JoinHandle<()>>>>;
+
+impl From<&RingEvent> for Topic {
+    fn from(ev: &RingEvent) -> Self {
+        match ev {
+            RingEvent::CustomMessage { .. } => Topic::CustomMessage,
+            RingEvent::PeerConnected(_)
+            | RingEvent::PeerDisconnected(_)
+            | RingEvent::SuccessorChanged(_)
+            | RingEvent::PredecessorChanged(_)
+            | RingEvent::TurnCredentialReceived(_) => Topic::PeerEvent,
+            RingEvent::VNodeStored(_)
+            | RingEvent::VNodeFetched(_)
+            | RingEvent::SubRingJoined { .. } => Topic::DhtEvent,
+        }
+    }
+}
+
+/// Build a pubsub handler, each subscription forwards events of `swarm` in its topic.
+pub(crate) fn build_pubsub_handler(swarm: Arc<Swarm>) -> PubSubHandler<Arc<Session>> {
+    let mut handler = PubSubHandler::default();
+    let subscriptions: Subscriptions = Default::default();
+    let subscriptions_1 = subscriptions.clone();
+
+    handler.add_subscription(
+        SUBSCRIPTION_NOTIFICATION,
+        (
+            Method::Subscribe.as_str(),
+            move |params: Params, _meta: Arc<Session>, subscriber: Subscriber| {
+                subscribe(params, subscriber, &swarm, &subscriptions)
+            },
+        ),
+        (
+            Method::Unsubscribe.as_str(),
+            move |id: SubscriptionId, _meta: Option<Arc<Session>>| {
+                let task = subscriptions_1.lock().unwrap().remove(&id);
+                let r = match task {
+                    Some(task) => {
+                        task.abort();
+                        Ok(Value::Bool(true))
+                    }
+                    None => Err(Error::from(ServerError::SubscriptionNotFound)),
+                };
+                futures::future::ready(r)
+            },
+        ),
+    );
+    handler
+}
+
+fn subscribe(
+    params: Params,
+    subscriber: Subscriber,
+    swarm: &Arc<Swarm>,
+    subscriptions: &Subscriptions,
+) {
+    let topic = params
+        .parse::<Vec<String>>()
+        .ok()
+        .and_then(|p| p.first().map(|t| Topic::try_from(t.as_str())));
+    let topic = match topic {
+        Some(Ok(t)) => t,
+        Some(Err(e)) => {
+            subscriber.reject(e.into()).ok();
+            return;
+        }
+        None => {
+            subscriber
+                .reject(Error::invalid_params("missing topic"))
+                .ok();
+            return;
+        }
+    };
+    let id = SubscriptionId::String(uuid::Uuid::new_v4().to_string());
+    let sink = match subscriber.assign_id(id.clone()) {
+        Ok(sink) => sink,
+        Err(_) => return,
+    };
+    let mut events = swarm.subscribe();
+    let sid = id.clone();
+    let task = tokio::spawn(async move {
+        while let Some(ev) = events.next().await {
+            if Topic::from(&ev) != topic {
+                continue;
+            }
+            let result = match serde_json::to_value(&ev) {
+                Ok(v) => v,
+                Err(e) => {
+                    log::error!("failed to serialize event: {}", e);
+                    continue;
+                }
+            };
+            let mut params = serde_json::Map::new();
+            params.insert("subscription".to_owned(), sid.clone().into());
+            params.insert("result".to_owned(), result);
+            if sink.notify(Params::Map(params)).is_err() {
+                log::debug!("subscription {:?} closed", sid);
+                break;
+            }
+        }
+    });
+    subscriptions.lock().unwrap().insert(id, task);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::SecretKey;
+    use crate::prelude::SessionManager;
+
+    #[tokio::test]
+    async fn test_subscribe_and_unsubscribe() {
+        let key = SecretKey::random();
+        let session = SessionManager::new_with_seckey(&key).unwrap();
+        let swarm = Arc::new(Swarm::new(
+            "stun://stun.l.google.com:19302",
+            key.address(),
+            session,
+        ));
+        let handler = build_pubsub_handler(swarm.clone());
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        let meta = Arc::new(Session::new(tx));
+
+        let resp = handler
+            .handle_request(
+                r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":["unknown"]}"#,
+                meta.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(resp.contains("error"), "{}", resp);
+
+        let resp = handler
+            .handle_request(
+                r#"{"jsonrpc":"2.0","id":2,"method":"subscribe","params":["peerEvent"]}"#,
+                meta.clone(),
+            )
+            .await
+            .unwrap();
+        let resp: serde_json::Value = serde_json::from_str(&resp).unwrap();
+        let sid = resp["result"].as_str().unwrap().to_owned();
+
+        let peer = SecretKey::random().address();
+        swarm
+            .event_bus()
+            .publish(RingEvent::VNodeStored(peer.into()));
+        swarm.event_bus().publish(RingEvent::PeerConnected(peer));
+        let notification: serde_json::Value =
+            serde_json::from_str(&rx.next().await.unwrap()).unwrap();
+        assert_eq!(notification["method"], SUBSCRIPTION_NOTIFICATION);
+        assert_eq!(notification["params"]["subscription"], sid);
+        assert_eq!(
+            notification["params"]["result"],
+            serde_json::to_value(RingEvent::PeerConnected(peer)).unwrap()
+        );
+
+        let resp = handler
+            .handle_request(
+                &format!(
+                    r#"{{"jsonrpc":"2.0","id":3,"method":"unsubscribe","params":["{}"]}}"#,
+                    sid
+                ),
+                meta,
+            )
+            .await
+            .unwrap();
+        assert!(resp.contains("true"), "{}", resp);
+    }
+}
@@ -6,9 +6,16 @@ use serde_json::Value as JsonValue;
 
 use crate::error::Error;
 use crate::error::Result;
+use crate::prelude::rings_core::dht::subring::SubRing;
+use crate::prelude::rings_core::dht::vnode::VirtualNode;
+use crate::prelude::rings_core::dht::Did;
+use crate::prelude::rings_core::dht::PeerRing;
 use crate::prelude::rings_core::message::Encoded;
+use crate::prelude::rings_core::message::PingReport;
+use crate::prelude::rings_core::message::RoutingTableReport;
 use crate::prelude::rings_core::prelude::web3::contract::tokens::Tokenizable;
 use crate::prelude::rings_core::prelude::web3::types::Address;
+use crate::prelude::rings_core::session::Session;
 use crate::prelude::rings_core::transports::Transport;
 use crate::processor;
 
@@ -60,6 +67,22 @@ impl From<processor::Peer> for Peer {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PendingTransport {
+    pub transport_id: String,
+    /// seconds since the transport was created
+    pub age: u64,
+}
+
+impl From<(Arc<Transport>, u128)> for PendingTransport {
+    fn from((transport, age_ms): (Arc<Transport>, u128)) -> Self {
+        Self {
+            transport_id: transport.id.to_string(),
+            age: (age_ms / 1000) as u64,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct TransportAndIce {
     pub transport_id: String,
@@ -96,3 +119,267 @@ impl From<(Arc<Transport>, Encoded)> for TransportAndIce {
         }
     }
 }
+
+/// Params of `storeValue`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StoreValue {
+    pub value: String,
+}
+
+/// Params of `fetchValue`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FetchValue {
+    pub did: String,
+}
+
+/// Params of `createSubRing` and `joinSubRing`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SubRingName {
+    pub name: String,
+}
+
+/// Params of `requestTurnCredential`, address of peer running TURN server.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TurnCredentialIssuer {
+    pub address: String,
+}
+
+/// Params of `ping`, address or registered name of node.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PingTarget {
+    pub address: String,
+}
+
+/// Params of `traceroute`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TracerouteTarget {
+    pub did: String,
+}
+
+/// Params of `getRoutingTable`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RoutingTableTarget {
+    pub did: String,
+}
+
+/// Response of `storeValue` and `createSubRing`, the did where the vnode is stored.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StoredValue {
+    pub did: String,
+}
+
+impl From<Did> for StoredValue {
+    fn from(did: Did) -> Self {
+        Self {
+            did: did_to_string(&did),
+        }
+    }
+}
+
+/// Response of `fetchValue`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FetchedValue {
+    pub did: String,
+    pub kind: String,
+    pub data: Vec<String>,
+}
+
+impl TryFrom<VirtualNode> for FetchedValue {
+    type Error = Error;
+    fn try_from(vnode: VirtualNode) -> Result<Self> {
+        let data = vnode
+            .data
+            .iter()
+            .map(|e| e.decode::<String>().map_err(|_| Error::DecodedError))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            did: did_to_string(&vnode.did()),
+            kind: format!("{:?}", vnode.kind),
+            data,
+        })
+    }
+}
+
+/// Response of `ping` and `traceroute`, the path of ping from local node to the node
+/// responsible for target, and back to local node.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PingResult {
+    pub target: String,
+    pub rtt_ms: u64,
+    pub hops: Vec<PingHopInfo>,
+}
+
+impl PingResult {
+    /// Node which reported the ping, it's the target only if target is reachable.
+    pub fn responder(&self) -> Option<&str> {
+        // hops end with local node when the report is back from another node
+        let i = self.hops.len().saturating_sub(2);
+        self.hops.get(i).map(|h| h.did.as_str())
+    }
+}
+
+/// A node passed by ping, `elapsed_ms` is since ping was sent, by clock of that node.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PingHopInfo {
+    pub did: String,
+    pub elapsed_ms: u64,
+}
+
+impl From<PingReport> for PingResult {
+    fn from(report: PingReport) -> Self {
+        let start = report.hops.first().map(|h| h.ts_ms).unwrap_or_default();
+        let elapsed = |ts_ms: u128| ts_ms.saturating_sub(start) as u64;
+        Self {
+            target: did_to_string(&report.target),
+            rtt_ms: report
+                .hops
+                .last()
+                .map(|h| elapsed(h.ts_ms))
+                .unwrap_or_default(),
+            hops: report
+                .hops
+                .iter()
+                .map(|h| PingHopInfo {
+                    did: did_to_string(&h.did),
+                    elapsed_ms: elapsed(h.ts_ms),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Response of `getRoutingTable`, `finger` has distinct dids of finger table.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct RoutingTable {
+    pub did: String,
+    pub predecessor: Option<String>,
+    pub successors: Vec<String>,
+    pub finger: Vec<String>,
+}
+
+impl From<RoutingTableReport> for RoutingTable {
+    fn from(report: RoutingTableReport) -> Self {
+        Self {
+            did: did_to_string(&report.did),
+            predecessor: report.predecessor.as_ref().map(did_to_string),
+            successors: report.successors.iter().map(did_to_string).collect(),
+            finger: report.finger.iter().map(did_to_string).collect(),
+        }
+    }
+}
+
+/// Response item of `listSubRings`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SubRingInfo {
+    pub name: String,
+    pub did: String,
+    pub creator: String,
+    pub admin: Option<String>,
+    pub members: Vec<String>,
+}
+
+impl From<SubRing> for SubRingInfo {
+    fn from(ring: SubRing) -> Self {
+        let mut members = ring
+            .finger
+            .list()
+            .iter()
+            .flatten()
+            .map(did_to_string)
+            .collect::<Vec<_>>();
+        members.sort();
+        members.dedup();
+        Self {
+            name: ring.name,
+            did: did_to_string(&ring.did),
+            creator: did_to_string(&ring.creator),
+            admin: ring.admin.as_ref().map(did_to_string),
+            members,
+        }
+    }
+}
+
+/// Response of `nodeInfo`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct NodeInfo {
+    pub version: String,
+    pub address: String,
+    pub session: SessionInfo,
+    pub routing: RoutingInfo,
+    pub storage: StorageInfo,
+    pub transports: Vec<TransportInfo>,
+    pub pending_transports: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SessionInfo {
+    pub authorizer: String,
+    pub session_address: String,
+    /// epoch ms, `None` if session never expires
+    pub expires_at: Option<u128>,
+    pub expired: bool,
+}
+
+impl From<&Session> for SessionInfo {
+    fn from(session: &Session) -> Self {
+        Self {
+            authorizer: session.auth.authorizer.into_token().to_string(),
+            session_address: session.auth.addr.into_token().to_string(),
+            expires_at: session.expires_at_ms(),
+            expired: session.is_expired(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RoutingInfo {
+    pub id: String,
+    pub successors: Vec<String>,
+    pub predecessor: Option<String>,
+    pub finger: Vec<Option<String>>,
+    pub fix_finger_index: u8,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StorageInfo {
+    pub storage: usize,
+    pub cache: usize,
+}
+
+impl From<&PeerRing> for RoutingInfo {
+    fn from(dht: &PeerRing) -> Self {
+        Self {
+            id: did_to_string(&dht.id),
+            successors: dht.successor.list().iter().map(did_to_string).collect(),
+            predecessor: dht.predecessor.as_ref().map(did_to_string),
+            finger: dht
+                .finger
+                .list()
+                .iter()
+                .map(|x| x.as_ref().map(did_to_string))
+                .collect(),
+            fix_finger_index: dht.fix_finger_index,
+        }
+    }
+}
+
+impl From<&PeerRing> for StorageInfo {
+    fn from(dht: &PeerRing) -> Self {
+        Self {
+            storage: dht.storage.len(),
+            cache: dht.cache.len(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TransportInfo {
+    pub address: String,
+    pub transport_id: String,
+    /// ice connection state, `None` if peer connection is not created
+    pub state: Option<String>,
+}
+
+fn did_to_string(did: &Did) -> String {
+    format!("{:?}", **did)
+}
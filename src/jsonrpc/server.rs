@@ -1,4 +1,6 @@
 #![warn(missing_docs)]
+
+use std::future::Future;
 use std::str::FromStr;
 
 use jsonrpc_core::Error;
@@ -7,12 +9,27 @@ use jsonrpc_core::MetaIoHandler;
 use jsonrpc_core::Params;
 use jsonrpc_core::Result;
 use jsonrpc_core::Value;
+use tokio::time::sleep;
+use tokio::time::Duration;
 
 use super::method::Method;
+use super::response::FetchValue;
+use super::response::FetchedValue;
 use super::response::Peer;
+use super::response::PendingTransport;
+use super::response::PingResult;
+use super::response::PingTarget;
+use super::response::RoutingTable;
+use super::response::RoutingTableTarget;
+use super::response::StoreValue;
+use super::response::StoredValue;
+use super::response::SubRingInfo;
+use super::response::SubRingName;
+use super::response::TracerouteTarget;
 use super::response::TransportAndIce;
+use super::response::TurnCredentialIssuer;
 use crate::error::Error as ServerError;
-use crate::prelude::rings_core::prelude::Address;
+use crate::prelude::rings_core::dht::Did;
 use crate::processor::Processor;
 
 pub(crate) async fn build_handler(handler: &mut MetaIoHandler<Processor>) {
@@ -23,9 +40,38 @@ pub(crate) async fn build_handler(handler: &mut MetaIoHandler<Processor>) {
     handler.add_method_with_meta(Method::AcceptAnswer.as_str(), accept_answer);
     handler.add_method_with_meta(Method::ListPeers.as_str(), list_peers);
     handler.add_method_with_meta(Method::Disconnect.as_str(), close_connection);
-    handler.add_method_with_meta(Method::SendTo.as_str(), send_message)
+    handler.add_method_with_meta(Method::ListPendings.as_str(), list_pendings);
+    handler.add_method_with_meta(
+        Method::ClosePendingTransport.as_str(),
+        close_pending_transport,
+    );
+    handler.add_method_with_meta(Method::SendTo.as_str(), send_message);
+    handler.add_method_with_meta(Method::StoreValue.as_str(), store_value);
+    handler.add_method_with_meta(Method::FetchValue.as_str(), fetch_value);
+    handler.add_method_with_meta(Method::CreateSubRing.as_str(), create_subring);
+    handler.add_method_with_meta(Method::JoinSubRing.as_str(), join_subring);
+    handler.add_method_with_meta(Method::ListSubRings.as_str(), list_subrings);
+    handler.add_method_with_meta(
+        Method::RequestTurnCredential.as_str(),
+        request_turn_credential,
+    );
+    handler.add_method_with_meta(Method::Ping.as_str(), ping);
+    handler.add_method_with_meta(Method::Traceroute.as_str(), traceroute);
+    handler.add_method_with_meta(Method::GetRoutingTable.as_str(), get_routing_table);
+    handler.add_method_with_meta(Method::NodeInfo.as_str(), node_info)
 }
 
+/// How long `fetchValue` waits for a remote vnode to arrive in local cache.
+const FETCH_VALUE_TIMEOUT_MS: u64 = 3000;
+const FETCH_VALUE_INTERVAL_MS: u64 = 100;
+/// How long `requestTurnCredential` waits for the credential to be issued.
+const TURN_CREDENTIAL_TIMEOUT_MS: u64 = 5000;
+/// How long `ping` and `traceroute` wait for the ping to be back.
+const PING_TIMEOUT_MS: u64 = 5000;
+const PING_INTERVAL_MS: u64 = 100;
+/// How long `getRoutingTable` waits for the table to be reported.
+const ROUTING_TABLE_TIMEOUT_MS: u64 = 5000;
+
 async fn connect_peer_via_http(params: Params, processor: Processor) -> Result<Value> {
     let p: Vec<String> = params.parse()?;
     let peer_url = p
@@ -56,11 +102,15 @@ async fn connect_with_address(params: Params, processor: Processor) -> Result<Va
     let address_str = p
         .first()
         .ok_or_else(|| Error::new(ErrorCode::InvalidParams))?;
+    let address = processor
+        .resolve_address(address_str)
+        .await
+        .map_err(|e| match e {
+            ServerError::InvalidAddress => Error::new(ErrorCode::InvalidParams),
+            e => Error::from(e),
+        })?;
     processor
-        .connect_with_address(
-            &Address::from_str(address_str).map_err(|_| Error::new(ErrorCode::InvalidParams))?,
-            true,
-        )
+        .connect_with_address(&address, true)
         .await
         .map_err(Error::from)?;
     Ok(Value::Null)
@@ -102,6 +152,33 @@ async fn close_connection(params: Params, processor: Processor) -> Result<Value>
     Ok(serde_json::json!({}))
 }
 
+async fn list_pendings(_params: Params, processor: Processor) -> Result<Value> {
+    let r = processor
+        .list_pendings()
+        .await?
+        .into_iter()
+        .map(|x| {
+            let age = processor
+                .swarm
+                .pending_transport_age(x.id)
+                .ok()
+                .flatten()
+                .unwrap_or(0);
+            (x, age).into()
+        })
+        .collect::<Vec<PendingTransport>>();
+    serde_json::to_value(&r).map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn close_pending_transport(params: Params, processor: Processor) -> Result<Value> {
+    let params: Vec<String> = params.parse()?;
+    let transport_id = params
+        .first()
+        .ok_or_else(|| Error::new(ErrorCode::InvalidParams))?;
+    processor.close_pending_transport(transport_id).await?;
+    Ok(serde_json::json!({}))
+}
+
 async fn send_message(params: Params, processor: Processor) -> Result<Value> {
     let params: serde_json::Map<String, Value> = params.parse()?;
     let destination = params
@@ -117,3 +194,128 @@ async fn send_message(params: Params, processor: Processor) -> Result<Value> {
     processor.send_message(destination, text.as_bytes()).await?;
     Ok(serde_json::json!({}))
 }
+
+async fn store_value(params: Params, processor: Processor) -> Result<Value> {
+    let params: StoreValue = params.parse()?;
+    let did = processor.store_value(&params.value).await?;
+    serde_json::to_value(&StoredValue::from(did))
+        .map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn fetch_value(params: Params, processor: Processor) -> Result<Value> {
+    let params: FetchValue = params.parse()?;
+    let mut vnode = processor.fetch_value(&params.did).await?;
+    let mut waited = 0;
+    while vnode.is_none() && waited < FETCH_VALUE_TIMEOUT_MS {
+        sleep(Duration::from_millis(FETCH_VALUE_INTERVAL_MS)).await;
+        waited += FETCH_VALUE_INTERVAL_MS;
+        vnode = processor.check_cache(&params.did).await?;
+    }
+    match vnode {
+        Some(vnode) => {
+            let r = FetchedValue::try_from(vnode)?;
+            serde_json::to_value(&r).map_err(|_| Error::from(ServerError::JsonSerializeError))
+        }
+        None => Ok(Value::Null),
+    }
+}
+
+async fn create_subring(params: Params, processor: Processor) -> Result<Value> {
+    let params: SubRingName = params.parse()?;
+    let did = processor.create_subring(&params.name).await?;
+    serde_json::to_value(&StoredValue::from(did))
+        .map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn join_subring(params: Params, processor: Processor) -> Result<Value> {
+    let params: SubRingName = params.parse()?;
+    processor.join_subring(&params.name).await?;
+    Ok(serde_json::json!({}))
+}
+
+async fn list_subrings(_params: Params, processor: Processor) -> Result<Value> {
+    let r = processor
+        .list_subrings()
+        .await
+        .into_iter()
+        .map(SubRingInfo::from)
+        .collect::<Vec<_>>();
+    serde_json::to_value(&r).map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn request_turn_credential(params: Params, processor: Processor) -> Result<Value> {
+    let params: TurnCredentialIssuer = params.parse()?;
+    let credential = send_and_wait(
+        processor.request_turn_credential(&params.address),
+        |_| processor.turn_credential(&params.address),
+        TURN_CREDENTIAL_TIMEOUT_MS,
+        FETCH_VALUE_INTERVAL_MS,
+    )
+    .await?;
+    // null if peer has no TURN server to issue credential
+    serde_json::to_value(&credential).map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn ping(params: Params, processor: Processor) -> Result<Value> {
+    let params: PingTarget = params.parse()?;
+    let address = processor.resolve_address(&params.address).await?;
+    wait_ping(&processor, address.into()).await
+}
+
+async fn traceroute(params: Params, processor: Processor) -> Result<Value> {
+    let params: TracerouteTarget = params.parse()?;
+    let did = Did::from_str(&params.did).map_err(|_| Error::from(ServerError::InvalidDid))?;
+    wait_ping(&processor, did).await
+}
+
+async fn wait_ping(processor: &Processor, target: Did) -> Result<Value> {
+    let report = send_and_wait(
+        processor.ping(target),
+        |id| Ok(processor.ping_report(*id)),
+        PING_TIMEOUT_MS,
+        PING_INTERVAL_MS,
+    )
+    .await?;
+    // null if ping is lost on the way
+    serde_json::to_value(&report.map(PingResult::from))
+        .map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+async fn get_routing_table(params: Params, processor: Processor) -> Result<Value> {
+    let params: RoutingTableTarget = params.parse()?;
+    let did = Did::from_str(&params.did).map_err(|_| Error::from(ServerError::InvalidDid))?;
+    let table = send_and_wait(
+        processor.request_routing_table(did),
+        |id| Ok(processor.routing_table(*id)),
+        ROUTING_TABLE_TIMEOUT_MS,
+        FETCH_VALUE_INTERVAL_MS,
+    )
+    .await?;
+    // null if request or report is lost on the way
+    serde_json::to_value(&table.map(RoutingTable::from))
+        .map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
+
+/// Send a request by `send`, then poll `take` with what `send` returns every `interval_ms`,
+/// until `take` returns a value or `timeout_ms` is passed.
+async fn send_and_wait<I, T>(
+    send: impl Future<Output = crate::error::Result<I>>,
+    take: impl Fn(&I) -> crate::error::Result<Option<T>>,
+    timeout_ms: u64,
+    interval_ms: u64,
+) -> Result<Option<T>> {
+    let id = send.await?;
+    let mut value = take(&id)?;
+    let mut waited = 0;
+    while value.is_none() && waited < timeout_ms {
+        sleep(Duration::from_millis(interval_ms)).await;
+        waited += interval_ms;
+        value = take(&id)?;
+    }
+    Ok(value)
+}
+
+async fn node_info(_params: Params, processor: Processor) -> Result<Value> {
+    let r = processor.node_info().await?;
+    serde_json::to_value(&r).map_err(|_| Error::from(ServerError::JsonSerializeError))
+}
@@ -4,21 +4,37 @@
 //! Sample:
 //! let client = Simpleclient::new(reqwest::Client::default(), "http://localhost:5000");
 //! client.call_method("test", params);
+#[cfg(feature = "client")]
+use std::pin::Pin;
 use std::sync::Arc;
 
+#[cfg(feature = "client")]
+use futures::SinkExt;
+#[cfg(feature = "client")]
+use futures::Stream;
+#[cfg(feature = "client")]
+use futures::StreamExt;
 use jsonrpc_core::Error;
 use jsonrpc_core::Params;
 use jsonrpc_core::Value;
 
 use super::request::parse_response;
 use super::request::RequestBuilder;
+use crate::jsonrpc::auth::Credential;
 use crate::prelude::reqwest::Client as HttpClient;
+#[cfg(feature = "client")]
+use crate::prelude::rings_core::prelude::url::Url;
+
+/// Stream of notification results of a subscription.
+#[cfg(feature = "client")]
+pub type SubscriptionStream = Pin<Box<dyn Stream<Item = RpcResult<Value>> + Send>>;
 
 /// SimpleClient
 #[derive(Clone)]
 pub struct SimpleClient {
     client: Arc<HttpClient>,
     url: String,
+    credential: Option<Credential>,
 }
 
 impl SimpleClient {
@@ -29,6 +45,7 @@ impl SimpleClient {
         Self {
             client,
             url: url.to_owned(),
+            credential: None,
         }
     }
 
@@ -38,6 +55,17 @@ impl SimpleClient {
         Self {
             client: Arc::new(HttpClient::default()),
             url: url.to_string(),
+            credential: None,
+        }
+    }
+
+    /// Create a new SimpleClient which authenticates each request with `credential`.
+    /// * url: remote jsonrpc_server url
+    pub fn new_with_credential(url: &str, credential: Option<Credential>) -> Self {
+        Self {
+            client: Arc::new(HttpClient::default()),
+            url: url.to_string(),
+            credential,
         }
     }
 
@@ -60,6 +88,87 @@ impl SimpleClient {
         Ok(())
     }
 
+    /// Websocket endpoint of remote jsonrpc_server, which is `/ws` on same host.
+    #[cfg(feature = "client")]
+    fn ws_url(&self) -> RpcResult<Url> {
+        let mut url = Url::parse(&self.url).map_err(|e| RpcError::Client(e.to_string()))?;
+        let scheme = match url.scheme() {
+            "https" => "wss",
+            _ => "ws",
+        };
+        url.set_scheme(scheme)
+            .map_err(|_| RpcError::Client("Invalid url scheme.".to_owned()))?;
+        url.set_path("/ws");
+        Ok(url)
+    }
+
+    /// JSONRpc subscribe request over websocket.
+    /// Returns a stream of results of notifications, ends when connection closed.
+    #[cfg(feature = "client")]
+    pub async fn subscribe(&self, msg: SubscribeMessage) -> RpcResult<SubscriptionStream> {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let mut request = self
+            .ws_url()?
+            .into_client_request()
+            .map_err(|e| RpcError::Client(e.to_string()))?;
+        if let Some(credential) = &self.credential {
+            for (name, value) in credential.headers("") {
+                request.headers_mut().insert(
+                    name,
+                    value
+                        .parse()
+                        .map_err(|_| RpcError::Client("Invalid credential.".to_owned()))?,
+                );
+            }
+        }
+        let (mut ws, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| RpcError::Client(e.to_string()))?;
+        let Subscription {
+            subscribe,
+            subscribe_params,
+            notification,
+            ..
+        } = msg.subscription;
+        let (id, request) = RequestBuilder::new().subscribe_request(subscribe, subscribe_params);
+        ws.send(WsMessage::Text(request))
+            .await
+            .map_err(|e| RpcError::Client(e.to_string()))?;
+
+        let sid = loop {
+            let resp = match ws.next().await {
+                Some(Ok(WsMessage::Text(resp))) => resp,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(RpcError::Client(e.to_string())),
+                None => return Err(RpcError::Client("Connection closed.".to_owned())),
+            };
+            let (resp_id, result, _, _) = parse_response(&resp)?;
+            if resp_id == id {
+                break result?;
+            }
+        };
+        log::debug!("subscribed: {}", sid);
+
+        let stream = ws.filter_map(move |msg| {
+            let notification = notification.clone();
+            async move {
+                let text = match msg {
+                    Ok(WsMessage::Text(text)) => text,
+                    Ok(_) => return None,
+                    Err(e) => return Some(Err(RpcError::Client(e.to_string()))),
+                };
+                match parse_response(&text) {
+                    Ok((_, result, Some(method), _)) if method == notification => Some(result),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
     async fn do_request(&self, msg: &RpcMessage) -> RpcResult<Value> {
         let mut request_builder = RequestBuilder::new();
         let request = match msg {
@@ -72,9 +181,13 @@ impl SimpleClient {
             }
         };
 
-        let resp = self
-            .client
-            .post(self.url.as_str())
+        let mut req = self.client.post(self.url.as_str());
+        if let Some(credential) = &self.credential {
+            for (name, value) in credential.headers(&request) {
+                req = req.header(name, value);
+            }
+        }
+        let resp = req
             .header(
                 http::header::CONTENT_TYPE,
                 http::header::HeaderValue::from_static("application/json"),
@@ -0,0 +1,118 @@
+#![warn(missing_docs)]
+//! Ethereum V3 keystore of node's secret key.
+//!
+//! Keys are written with scrypt and AES-128-CTR. Keystores using pbkdf2,
+//! as exported by some wallets, can be read as well.
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::prelude::SecretKey;
+
+/// Encrypt `key` with `password` and write it to `path`, an existing file is never overwritten.
+pub fn encrypt(key: &SecretKey, path: impl AsRef<Path>, password: &str) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Err(Error::Keystore(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| Error::Keystore(format!("invalid path {}", path.display())))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).map_err(|e| Error::Keystore(e.to_string()))?;
+    eth_keystore::encrypt_key(
+        dir,
+        &mut rand::thread_rng(),
+        key.serialize(),
+        password,
+        Some(name),
+    )
+    .map_err(|e| Error::Keystore(e.to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| Error::Keystore(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Decrypt keystore at `path` with `password`.
+pub fn decrypt(path: impl AsRef<Path>, password: &str) -> Result<SecretKey> {
+    let path = path.as_ref();
+    let bytes = eth_keystore::decrypt_key(path, password)
+        .map_err(|e| Error::Keystore(format!("{}: {}", path.display(), e)))?;
+    SecretKey::try_from(hex::encode(bytes).as_str()).map_err(|e| Error::Keystore(e.to_string()))
+}
+
+/// Read password from first line of `password_file`, or prompt for it on tty if not given.
+pub fn read_password(password_file: Option<&Path>, prompt: &str) -> Result<String> {
+    match password_file {
+        Some(path) => fs::read_to_string(path)
+            .map(|s| s.lines().next().unwrap_or_default().to_owned())
+            .map_err(|e| Error::Keystore(format!("{}: {}", path.display(), e))),
+        None => rpassword::prompt_password(prompt).map_err(|e| Error::Keystore(e.to_string())),
+    }
+}
+
+/// Like [read_password], but prompt twice and check they match, for setting a new password.
+pub fn read_new_password(password_file: Option<&Path>) -> Result<String> {
+    if password_file.is_some() {
+        return read_password(password_file, "");
+    }
+    let password = read_password(None, "New passphrase: ")?;
+    let repeated = read_password(None, "Repeat passphrase: ")?;
+    if password != repeated {
+        return Err(Error::Keystore("passphrases do not match".to_owned()));
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::prelude::uuid::Uuid;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("rings-keystore-{}", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let key = SecretKey::random();
+        let path = temp_path();
+        encrypt(&key, &path, "passphrase").unwrap();
+        assert_eq!(
+            decrypt(&path, "passphrase").unwrap().address(),
+            key.address()
+        );
+        assert!(decrypt(&path, "wrong").is_err());
+        // never overwrite an existing keystore
+        assert!(encrypt(&SecretKey::random(), &path, "passphrase").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_decrypt_pbkdf2_keystore() {
+        // test vector of Web3 Secret Storage Definition
+        let content = r#"{"crypto":{"cipher":"aes-128-ctr","cipherparams":{"iv":"6087dab2f9fdbbfaddc31a909735c1e6"},"ciphertext":"5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46","kdf":"pbkdf2","kdfparams":{"c":262144,"dklen":32,"prf":"hmac-sha256","salt":"ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"},"mac":"517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"},"id":"3198bc9c-6672-5ab3-d995-4942343ae5b6","version":3}"#;
+        let path = temp_path();
+        fs::write(&path, content).unwrap();
+        let key = decrypt(&path, "testpassword").unwrap();
+        assert_eq!(
+            key.to_string(),
+            "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -134,18 +134,28 @@
 //! ```
 
 #![feature(async_closure)]
+#[cfg(feature = "client")]
+pub mod bootstrap;
 #[cfg(feature = "browser")]
 pub mod browser;
 #[cfg(feature = "client")]
 pub mod cli;
+#[cfg(feature = "client")]
+pub mod config;
+#[cfg(feature = "client")]
+pub mod crawler;
 pub mod error;
 #[cfg(feature = "client")]
 pub mod ethereum;
 pub mod jsonrpc;
 pub mod jsonrpc_client;
 #[cfg(feature = "client")]
+pub mod keystore;
+#[cfg(feature = "client")]
 pub mod logger;
 pub mod prelude;
 pub mod processor;
 #[cfg(feature = "client")]
 pub mod service;
+#[cfg(feature = "client")]
+pub mod storage;
@@ -1,36 +1,85 @@
-use chrono::Local;
+//! Logger of `rings-node` and `rings-node-daemon`, built on `tracing`.
+//!
+//! Records of `log` are collected too, so messages handled by `rings-core` are logged
+//! within span `payload`, which carries `tx_id`, origin, destination, hop and message.
+//! With json format, each line has fields of its spans, and lines of several nodes can
+//! be merged by `tx_id` into the path of a payload.
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
 use clap::ArgEnum;
-use log::Level;
-use log::LevelFilter;
-use log::Log;
-use log::Metadata;
-use log::Record;
-use log::SetLoggerError;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
 
-pub struct Logger;
+/// Output format of logs.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[clap(rename_all = "kebab-case")]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// human readable lines
+    Text,
+    /// one json object per line
+    Json,
+}
 
-impl Log for Logger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Trace
+impl Default for LogFormat {
+    fn default() -> Self {
+        Self::Text
     }
+}
 
-    fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            println!(
-                "{} [{}] - {}",
-                Local::now().format("%Y-%m-%dT%H:%M:%S"),
-                record.level(),
-                record.args()
-            );
-        }
-    }
+/// Logging, the level of modules not listed is given by `--log-level`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// output format
+    pub format: LogFormat,
+    /// level of modules, like `"rings_core::message" = "debug"`
+    pub modules: BTreeMap<String, String>,
+}
 
-    fn flush(&self) {}
+impl LogConfig {
+    /// Filter directives of modules, or the `module=level` which is invalid.
+    pub fn directives(&self) -> Result<Vec<Directive>, String> {
+        self.modules
+            .iter()
+            .map(|(module, level)| {
+                let directive = format!("{}={}", module, level);
+                match LevelFilter::from_str(level) {
+                    Ok(_) => Directive::from_str(&directive).map_err(|_| directive),
+                    Err(_) => Err(directive),
+                }
+            })
+            .collect()
+    }
 }
 
+pub struct Logger;
+
 impl Logger {
-    pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
-        log::set_boxed_logger(Box::new(Logger)).map(|()| log::set_max_level(level))
+    pub fn init(level: LogLevel, config: &LogConfig) -> anyhow::Result<()> {
+        let mut filter = EnvFilter::default().add_directive(LevelFilter::from(level).into());
+        let directives = config
+            .directives()
+            .map_err(|d| anyhow::anyhow!("invalid log directive {}", d))?;
+        for directive in directives {
+            filter = filter.add_directive(directive);
+        }
+        let builder = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_ansi(false);
+        match config.format {
+            LogFormat::Text => builder.try_init(),
+            LogFormat::Json => builder
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .try_init(),
+        }
+        .map_err(anyhow::Error::msg)
     }
 }
 
@@ -57,3 +106,16 @@ impl From<LogLevel> for log::LevelFilter {
         }
     }
 }
+
+impl From<LogLevel> for LevelFilter {
+    fn from(val: LogLevel) -> Self {
+        match val {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
@@ -8,14 +8,29 @@ use jsonrpc_core::Metadata;
 
 use crate::error::Error;
 use crate::error::Result;
+#[cfg(feature = "client")]
+use crate::ethereum::Registry;
 use crate::jsonrpc::method;
+use crate::jsonrpc::response::NodeInfo;
 use crate::jsonrpc::response::TransportAndIce;
+use crate::jsonrpc::response::TransportInfo;
 use crate::jsonrpc_client::SimpleClient;
+use crate::prelude::reqwest::Client as HttpClient;
+use crate::prelude::rings_core::dht::subring::SubRing;
+use crate::prelude::rings_core::dht::vnode::VirtualNode;
+use crate::prelude::rings_core::dht::Did;
 use crate::prelude::rings_core::dht::Stabilization;
 use crate::prelude::rings_core::message::Encoded;
 use crate::prelude::rings_core::message::Message;
 use crate::prelude::rings_core::message::MessageHandler;
 use crate::prelude::rings_core::message::PayloadSender;
+use crate::prelude::rings_core::message::PingOperator;
+use crate::prelude::rings_core::message::PingReport;
+use crate::prelude::rings_core::message::RoutingTableOperator;
+use crate::prelude::rings_core::message::RoutingTableReport;
+use crate::prelude::rings_core::message::SubRingOperator;
+use crate::prelude::rings_core::message::TChordStorage;
+use crate::prelude::rings_core::message::TurnCredentialOperator;
 use crate::prelude::rings_core::prelude::uuid;
 use crate::prelude::rings_core::prelude::web3::contract::tokens::Tokenizable;
 use crate::prelude::rings_core::prelude::web3::ethabi::Token;
@@ -24,6 +39,7 @@ use crate::prelude::rings_core::prelude::RTCSdpType;
 use crate::prelude::rings_core::swarm::Swarm;
 use crate::prelude::rings_core::swarm::TransportManager;
 use crate::prelude::rings_core::transports::Transport;
+use crate::prelude::rings_core::turn::TurnCredential;
 use crate::prelude::rings_core::types::ice_transport::IceTransport;
 use crate::prelude::rings_core::types::ice_transport::IceTrickleScheme;
 
@@ -36,6 +52,9 @@ pub struct Processor {
     pub msg_handler: Arc<MessageHandler>,
     /// a stabilization instane,
     pub stabilization: Arc<Stabilization>,
+    /// registry of node records on Ethereum, to connect nodes not reachable in ring
+    #[cfg(feature = "client")]
+    pub registry: Option<Arc<Registry>>,
 }
 
 #[cfg(feature = "client")]
@@ -49,11 +68,20 @@ impl From<(Arc<Swarm>, Arc<MessageHandler>, Arc<Stabilization>)> for Processor {
             swarm,
             msg_handler,
             stabilization,
+            #[cfg(feature = "client")]
+            registry: None,
         }
     }
 }
 
 impl Processor {
+    /// Look up nodes in `registry` when they can't be connected through the ring.
+    #[cfg(feature = "client")]
+    pub fn with_registry(mut self, registry: Option<Arc<Registry>>) -> Self {
+        self.registry = registry;
+        self
+    }
+
     /// Get current address
     pub fn address(&self) -> Address {
         self.swarm.address()
@@ -67,6 +95,10 @@ impl Processor {
     /// 4. PeerB: send the handshake info to PeerA.
     /// 5. PeerA: accept_answer.
     pub async fn create_offer(&self) -> Result<(Arc<Transport>, Encoded)> {
+        self.msg_handler
+            .ensure_capacity()
+            .await
+            .map_err(Error::ConnectError)?;
         let transport = self
             .swarm
             .new_transport()
@@ -98,6 +130,10 @@ impl Processor {
     pub async fn connect_peer_via_http(&self, peer_url: &str) -> Result<Arc<Transport>> {
         // request remote offer and sand answer to remote
         log::debug!("connect_peer_via_http: {}", peer_url);
+        self.msg_handler
+            .ensure_capacity()
+            .await
+            .map_err(Error::ConnectError)?;
         let transport = self
             .swarm
             .new_transport()
@@ -156,6 +192,73 @@ impl Processor {
         Ok(addr.to_string())
     }
 
+    /// Connect peer with the signaling endpoint `POST /sdp` of a remote node.
+    /// * sdp_url: url of the signaling endpoint.
+    /// * token: token of the signaling endpoint, if it's required.
+    pub async fn connect_peer_via_signaling(
+        &self,
+        sdp_url: &str,
+        token: Option<&str>,
+    ) -> Result<Arc<Transport>> {
+        log::debug!("connect_peer_via_signaling: {}", sdp_url);
+        self.msg_handler
+            .ensure_capacity()
+            .await
+            .map_err(Error::ConnectError)?;
+        let transport = self
+            .swarm
+            .new_transport()
+            .await
+            .map_err(|_| Error::NewTransportError)?;
+        if let Err(e) = self
+            .do_connect_peer_via_signaling(&transport, sdp_url, token)
+            .await
+        {
+            transport
+                .close()
+                .await
+                .map_err(Error::CloseTransportError)?;
+            return Err(e);
+        }
+        Ok(transport)
+    }
+
+    async fn do_connect_peer_via_signaling(
+        &self,
+        transport: &Arc<Transport>,
+        sdp_url: &str,
+        token: Option<&str>,
+    ) -> Result<()> {
+        let offer = transport
+            .get_handshake_info(self.swarm.session_manager(), RTCSdpType::Offer)
+            .await
+            .map_err(Error::CreateOffer)?
+            .to_string();
+        let mut request = HttpClient::default().post(sdp_url).body(offer);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| Error::Signaling(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(Error::Signaling(resp.status().to_string()));
+        }
+        let answer = resp
+            .text()
+            .await
+            .map_err(|e| Error::Signaling(e.to_string()))?;
+        let addr = transport
+            .register_remote_info(Encoded::from_encoded_str(answer.trim()))
+            .await
+            .map_err(Error::RegisterIceError)?;
+        self.swarm
+            .register(&addr, Arc::clone(transport))
+            .await
+            .map_err(Error::RegisterIceError)
+    }
+
     /// Answer an Offer.
     /// The process of manually handshake is:
     /// 1. PeerA: create_offer
@@ -165,6 +268,10 @@ impl Processor {
     /// 5. PeerA: accept_answer.
     pub async fn answer_offer(&self, ice_info: &str) -> Result<(Arc<Transport>, Encoded)> {
         log::info!("connect peer via ice: {}", ice_info);
+        self.msg_handler
+            .ensure_capacity()
+            .await
+            .map_err(Error::ConnectError)?;
         let transport = self.swarm.new_transport().await.map_err(|e| {
             log::error!("new_transport failed: {}", e);
             Error::NewTransportError
@@ -191,11 +298,15 @@ impl Processor {
         address: &Address,
         wait_for_open: bool,
     ) -> Result<Peer> {
-        let transport = self
-            .msg_handler
-            .connect(address)
-            .await
-            .map_err(Error::ConnectWithAddressError)?;
+        let transport = match self.msg_handler.connect(address).await {
+            Ok(transport) => transport,
+            #[cfg(feature = "client")]
+            Err(e) if self.registry.is_some() => {
+                log::debug!("connect {:?} in ring failed: {}, try registry", address, e);
+                self.connect_via_registry(address).await?
+            }
+            Err(e) => return Err(Error::ConnectWithAddressError(e)),
+        };
         log::debug!("wait for transport connected");
         if wait_for_open {
             transport
@@ -206,6 +317,70 @@ impl Processor {
         Ok(Peer::from((*address, transport)))
     }
 
+    /// Resolve a name registered in registry, or parse it as an address.
+    #[cfg(feature = "client")]
+    pub async fn resolve_address(&self, name: &str) -> Result<Address> {
+        if let Ok(address) = Address::from_str(name) {
+            return Ok(address);
+        }
+        let registry = self.registry.as_ref().ok_or(Error::InvalidAddress)?;
+        registry
+            .resolve_name(name)
+            .await?
+            .ok_or_else(|| Error::Registry(format!("{} is not registered", name)))
+    }
+
+    /// Connect bootstrap urls in record of `address`, until the node is connected.
+    /// An url of another node is used to join the ring, then the node is connected through it.
+    #[cfg(feature = "client")]
+    async fn connect_via_registry(&self, address: &Address) -> Result<Arc<Transport>> {
+        let registry = self.registry.as_ref().ok_or(Error::InvalidAddress)?;
+        let record = registry
+            .lookup(address)
+            .await?
+            .ok_or_else(|| Error::Registry(format!("{:?} is not registered", address)))?;
+        let mut last_err = Error::Registry(format!("no bootstrap of {:?} reachable", address));
+        for url in record.bootstrap.iter() {
+            let transport = match self.connect_peer_via_http(url).await {
+                Ok(transport) => transport,
+                Err(e) => {
+                    log::warn!("connect bootstrap {} of {:?} failed: {}", url, address, e);
+                    last_err = e;
+                    continue;
+                }
+            };
+            let pubkey = transport.pubkey().await;
+            if pubkey.address() != *address {
+                transport
+                    .wait_for_data_channel_open()
+                    .await
+                    .map_err(Error::ConnectWithAddressError)?;
+                return self
+                    .msg_handler
+                    .connect(address)
+                    .await
+                    .map_err(Error::ConnectWithAddressError);
+            }
+            let matched = match transport.session_pubkey().await {
+                Some(session_pubkey) => record.matches_session_pubkey(&session_pubkey),
+                None => false,
+            };
+            if !matched {
+                transport
+                    .close()
+                    .await
+                    .map_err(Error::CloseTransportError)?;
+                self.swarm.remove_transport(address);
+                return Err(Error::Registry(format!(
+                    "session key of {:?} mismatches its record",
+                    address
+                )));
+            }
+            return Ok(transport);
+        }
+        Err(last_err)
+    }
+
     async fn handshake(&self, transport: &Arc<Transport>, data: &str) -> Result<Encoded> {
         // get offer from remote and send answer back
         let hs_info = Encoded::from_encoded_str(data);
@@ -253,9 +428,6 @@ impl Processor {
             .register(&addr, transport.clone())
             .await
             .map_err(Error::RegisterIceError)?;
-        if let Err(e) = self.swarm.pop_pending_transport(transport.id) {
-            log::warn!("pop_pending_transport err: {}", e)
-        };
         Ok(Peer::from((addr, transport)))
     }
 
@@ -342,6 +514,135 @@ impl Processor {
             .map_err(Error::SendMessage)?;
         Ok(())
     }
+
+    /// Collect routing state, session, transports and storage of local node.
+    pub async fn node_info(&self) -> Result<NodeInfo> {
+        let dht = self.msg_handler.dht_snapshot().await;
+        let session = self
+            .swarm
+            .session_manager()
+            .session()
+            .map_err(|_| Error::InternalError)?;
+        let mut transports = vec![];
+        for (address, transport) in self.swarm.get_transports() {
+            let state = transport
+                .ice_connection_state()
+                .await
+                .map(|s| format!("{:?}", s));
+            transports.push(TransportInfo {
+                address: address.into_token().to_string(),
+                transport_id: transport.id.to_string(),
+                state,
+            });
+        }
+        let pending_transports = self
+            .swarm
+            .pending_transports()
+            .await
+            .map_err(|_| Error::InternalError)?
+            .len();
+        Ok(NodeInfo {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            address: self.address().into_token().to_string(),
+            session: (&session).into(),
+            routing: (&dht).into(),
+            storage: (&dht).into(),
+            transports,
+            pending_transports,
+        })
+    }
+
+    /// Store a string value on DHT, return the did of vnode.
+    pub async fn store_value(&self, value: &str) -> Result<Did> {
+        let vnode: VirtualNode = value.to_owned().try_into().map_err(Error::StoreValue)?;
+        let did = vnode.did();
+        self.msg_handler
+            .store(vnode)
+            .await
+            .map_err(Error::StoreValue)?;
+        Ok(did)
+    }
+
+    /// Fetch vnode with did, return it if it's in local cache.
+    /// Otherwise a search request is sent, and the vnode will be cached after found.
+    pub async fn fetch_value(&self, did: &str) -> Result<Option<VirtualNode>> {
+        let did = Did::from_str(did).map_err(|_| Error::InvalidDid)?;
+        if let Some(vnode) = self.msg_handler.check_cache(&did).await {
+            return Ok(Some(vnode));
+        }
+        self.msg_handler
+            .fetch(&did)
+            .await
+            .map_err(Error::FetchValue)?;
+        Ok(self.msg_handler.check_cache(&did).await)
+    }
+
+    /// Check local cache of vnode with did.
+    pub async fn check_cache(&self, did: &str) -> Result<Option<VirtualNode>> {
+        let did = Did::from_str(did).map_err(|_| Error::InvalidDid)?;
+        Ok(self.msg_handler.check_cache(&did).await)
+    }
+
+    /// Create a subring with name, return the did of subring.
+    pub async fn create_subring(&self, name: &str) -> Result<Did> {
+        let subring = SubRing::new(name, &self.address().into()).map_err(Error::SubRing)?;
+        self.msg_handler
+            .create(name)
+            .await
+            .map_err(Error::SubRing)?;
+        Ok(subring.did)
+    }
+
+    /// Join a subring with name.
+    pub async fn join_subring(&self, name: &str) -> Result<()> {
+        self.msg_handler.join(name).await.map_err(Error::SubRing)
+    }
+
+    /// List subrings stored on local node.
+    pub async fn list_subrings(&self) -> Vec<SubRing> {
+        self.msg_handler.list().await
+    }
+
+    /// Request a credential of TURN server from a connected peer.
+    /// The credential will be available by [Processor::turn_credential] after it's issued.
+    pub async fn request_turn_credential(&self, address: &str) -> Result<()> {
+        let address = Address::from_str(address).map_err(|_| Error::InvalidAddress)?;
+        self.msg_handler
+            .request_turn_credential(&address)
+            .await
+            .map_err(Error::TurnCredential)
+    }
+
+    /// Credential of TURN server issued by peer, if it's not expired.
+    pub fn turn_credential(&self, address: &str) -> Result<Option<TurnCredential>> {
+        let address = Address::from_str(address).map_err(|_| Error::InvalidAddress)?;
+        Ok(self.msg_handler.turn_credential(&address))
+    }
+
+    /// Route a ping to the node responsible for `target`, return id of the ping.
+    /// Hops of the ping will be available by [Processor::ping_report] after it's back.
+    pub async fn ping(&self, target: Did) -> Result<u128> {
+        self.msg_handler.ping(&target).await.map_err(Error::Ping)
+    }
+
+    /// Take report of ping `id`, if it's back.
+    pub fn ping_report(&self, id: u128) -> Option<PingReport> {
+        self.msg_handler.take_ping_report(id)
+    }
+
+    /// Ask routing table of the node responsible for `target`, return id of the request.
+    /// The table will be available by [Processor::routing_table] after it's reported.
+    pub async fn request_routing_table(&self, target: Did) -> Result<u128> {
+        self.msg_handler
+            .request_routing_table(&target)
+            .await
+            .map_err(Error::RoutingTable)
+    }
+
+    /// Take routing table reported for request `id`, if it's received.
+    pub fn routing_table(&self, id: u128) -> Option<RoutingTableReport> {
+        self.msg_handler.take_routing_table(id)
+    }
 }
 
 /// Peer struct
@@ -377,6 +678,8 @@ mod test {
     use futures::lock::Mutex;
 
     use super::*;
+    use crate::jsonrpc::response::did_to_string;
+    use crate::jsonrpc::response::PingResult;
     use crate::prelude::*;
 
     fn new_processor() -> Processor {
@@ -492,6 +795,90 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_processor_store_and_fetch_value() {
+        let processor = new_processor();
+        assert!(processor.fetch_value("not a did").await.is_err());
+        let did = processor.store_value("hello rings").await.unwrap();
+        let did_str = format!("{:?}", *did);
+        assert!(processor.check_cache(&did_str).await.unwrap().is_none());
+        let vnode = processor.fetch_value(&did_str).await.unwrap().unwrap();
+        assert_eq!(vnode.did(), did);
+        let data: String = vnode.data[0].decode().unwrap();
+        assert_eq!(data, "hello rings");
+    }
+
+    #[tokio::test]
+    async fn test_processor_create_and_list_subrings() {
+        let processor = new_processor();
+        assert!(processor.list_subrings().await.is_empty());
+        let did = processor.create_subring("rings").await.unwrap();
+        processor.join_subring("rings").await.unwrap();
+        let subrings = processor.list_subrings().await;
+        assert_eq!(subrings.len(), 1);
+        assert_eq!(subrings[0].did, did);
+        assert_eq!(subrings[0].name, "rings");
+        assert_eq!(subrings[0].creator, processor.address().into());
+    }
+
+    #[tokio::test]
+    async fn test_processor_node_info() {
+        let processor = new_processor();
+        processor.create_offer().await.unwrap();
+        processor.store_value("hello rings").await.unwrap();
+        let info = processor.node_info().await.unwrap();
+        assert_eq!(info.address, processor.address().into_token().to_string());
+        assert_eq!(info.routing.finger.len(), 160);
+        assert!(info.routing.successors.is_empty());
+        assert!(info.routing.predecessor.is_none());
+        assert_eq!(info.storage.storage, 1);
+        assert_eq!(info.storage.cache, 0);
+        assert!(info.transports.is_empty());
+        assert_eq!(info.pending_transports, 1);
+        assert!(!info.session.expired);
+        assert!(info.session.expires_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_processor_ping_self() {
+        let processor = new_processor();
+        let did: Did = processor.address().into();
+        let id = processor.ping(did).await.unwrap();
+        let report = processor.ping_report(id).unwrap();
+        assert_eq!(report.target, did);
+        assert_eq!(report.hops.len(), 1);
+        assert_eq!(report.hops[0].did, did);
+        assert!(processor.ping_report(id).is_none());
+        let result = PingResult::from(report);
+        assert_eq!(result.responder(), Some(result.target.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_processor_ping_unreachable() {
+        let processor = new_processor();
+        let did: Did = SecretKey::random().address().into();
+        // no peer is connected, local node is responsible for any did
+        let id = processor.ping(did).await.unwrap();
+        let result = PingResult::from(processor.ping_report(id).unwrap());
+        assert_eq!(result.target, did_to_string(&did));
+        assert_eq!(
+            result.responder(),
+            Some(did_to_string(&processor.address().into()).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_processor_routing_table_self() {
+        let processor = new_processor();
+        let did: Did = processor.address().into();
+        let id = processor.request_routing_table(did).await.unwrap();
+        let table = processor.routing_table(id).unwrap();
+        assert_eq!(table.did, did);
+        assert!(table.successors.is_empty());
+        assert!(table.predecessor.is_none());
+        assert!(processor.routing_table(id).is_none());
+    }
+
     struct MsgCallbackStruct {
         msgs: Arc<Mutex<Vec<String>>>,
     }
@@ -5,6 +5,9 @@ use axum::response::Response;
 #[derive(Debug)]
 pub enum HttpError {
     BadRequest,
+    Unauthorized,
+    Forbidden,
+    TooManyRequests,
     Internal,
 }
 
@@ -12,6 +15,9 @@ impl IntoResponse for HttpError {
     fn into_response(self) -> Response {
         let (code, msg) = match self {
             HttpError::BadRequest => (StatusCode::BAD_REQUEST, "Bad Request"),
+            HttpError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
+            HttpError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            HttpError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too Many Requests"),
             HttpError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal error"),
         };
 
@@ -36,3 +42,13 @@ impl From<anyhow::Error> for HttpError {
         HttpError::Internal
     }
 }
+
+impl From<crate::error::Error> for HttpError {
+    fn from(e: crate::error::Error) -> Self {
+        match e {
+            crate::error::Error::Unauthorized => HttpError::Unauthorized,
+            crate::error::Error::PermissionDenied => HttpError::Forbidden,
+            _ => HttpError::Internal,
+        }
+    }
+}
@@ -1,9 +1,16 @@
 use std::collections::HashMap;
+use std::fs;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::Mutex;
 
+use stun::attributes::ATTR_REALM;
+use stun::attributes::ATTR_USERNAME;
+use stun::integrity::MessageIntegrity;
+use stun::message::Message;
+use stun::textattrs::TextAttribute;
 use tokio::net::UdpSocket;
 use tokio::time::Duration;
 use turn::auth::*;
@@ -12,14 +19,171 @@ use turn::server::config::*;
 use turn::server::*;
 use turn::Error;
 use webrtc_util::vnet::net::*;
+use webrtc_util::Conn;
+
+use super::turn_tcp::StreamConn;
+use crate::config::TurnConfig;
+use crate::prelude::rings_core::async_trait;
+use crate::prelude::rings_core::turn as rest;
+use crate::prelude::rings_core::utils;
+
+/// Users are forgotten when all of their credentials expired, once there are this many of them.
+const MAX_TRACKED_USERS: usize = 1024;
+
+/// Credentials of TURN REST API, issued by [rings_core::turn::TurnCredentialIssuer].
+/// A client address is counted to quota of user only when its message is authenticated,
+/// by [RestAuthConn] before the message is handled by server.
+struct RestAuth {
+    secret: String,
+    quota: usize,
+    /// seconds an issued credential lives at most
+    ttl: u64,
+    /// expiry of credential used by each authenticated client address, grouped by user
+    clients: Mutex<HashMap<String, HashMap<SocketAddr, u64>>>,
+}
+
+impl RestAuth {
+    fn new(secret: &str, quota: usize, ttl: u64) -> Self {
+        Self {
+            secret: secret.to_owned(),
+            quota,
+            ttl,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Parse `username`, it should be neither expired nor expiring later than it's issued.
+    fn parse<'a>(&self, username: &'a str, now: u64) -> Result<(u64, &'a str), Error> {
+        let (expires_at, user) = rest::parse_username(username).ok_or(Error::ErrNoSuchUser)?;
+        if expires_at <= now {
+            return Err(Error::Other(format!("credential of {} is expired", user)));
+        }
+        if expires_at > now + self.ttl {
+            return Err(Error::Other(format!(
+                "credential of {} expires too late",
+                user
+            )));
+        }
+        Ok((expires_at, user))
+    }
+
+    /// Check integrity of a STUN message signed with credential of REST API,
+    /// and count its source to quota of the user.
+    fn authenticate(&self, msg: &[u8], src_addr: SocketAddr, now: u64) -> Result<(), Error> {
+        let mut m = Message::new();
+        m.raw = msg.to_vec();
+        m.decode()
+            .map_err(|e| Error::Other(format!("invalid message: {}", e)))?;
+        let text = |attr| {
+            TextAttribute::get_from_as(&m, attr)
+                .map(|t| t.text)
+                .map_err(|e| Error::Other(format!("missing attribute: {}", e)))
+        };
+        let (username, realm) = (text(ATTR_USERNAME)?, text(ATTR_REALM)?);
+        self.parse(&username, now)?;
+        let key = generate_auth_key(
+            &username,
+            &realm,
+            &rest::credential(&self.secret, &username),
+        );
+        MessageIntegrity(key)
+            .check(&mut m.clone())
+            .map_err(|e| Error::Other(format!("integrity check failed: {}", e)))?;
+        self.admit(&username, src_addr, now)
+    }
+
+    /// Count `src_addr` to quota of user of `username`, which should be authenticated.
+    fn admit(&self, username: &str, src_addr: SocketAddr, now: u64) -> Result<(), Error> {
+        let (expires_at, user) = self.parse(username, now)?;
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_TRACKED_USERS {
+            clients.retain(|_, addrs| addrs.values().any(|exp| *exp > now));
+        }
+        let addrs = clients.entry(user.to_owned()).or_default();
+        addrs.retain(|_, exp| *exp > now);
+        if !addrs.contains_key(&src_addr) && addrs.len() >= self.quota {
+            return Err(Error::Other(format!("{} is over quota", user)));
+        }
+        addrs.insert(src_addr, expires_at);
+        Ok(())
+    }
+
+    /// Check expiry of `username` and that `src_addr` is admitted, return its password.
+    fn password(&self, username: &str, src_addr: SocketAddr, now: u64) -> Result<String, Error> {
+        let (_, user) = self.parse(username, now)?;
+        let admitted = self
+            .clients
+            .lock()
+            .unwrap()
+            .get(user)
+            .and_then(|addrs| addrs.get(&src_addr))
+            .map(|exp| *exp > now)
+            .unwrap_or(false);
+        if !admitted {
+            return Err(Error::Other(format!(
+                "{} is not authenticated or over quota",
+                user
+            )));
+        }
+        Ok(rest::credential(&self.secret, username))
+    }
+}
+
+/// Conn of turn server, which authenticates messages signed with credentials of REST API,
+/// before they are handled by server.
+struct RestAuthConn {
+    conn: Arc<dyn Conn + Send + Sync>,
+    auth: Arc<RestAuth>,
+}
+
+#[async_trait]
+impl Conn for RestAuthConn {
+    async fn connect(&self, addr: SocketAddr) -> Result<(), webrtc_util::Error> {
+        self.conn.connect(addr).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize, webrtc_util::Error> {
+        self.recv_from(buf).await.map(|(n, _)| n)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), webrtc_util::Error> {
+        let (n, addr) = self.conn.recv_from(buf).await?;
+        let now = (utils::get_epoch_ms() / 1000) as u64;
+        if let Err(e) = self.auth.authenticate(&buf[..n], addr, now) {
+            log::trace!("message from {} is not authenticated: {}", addr, e);
+        }
+        Ok((n, addr))
+    }
+
+    async fn send(&self, buf: &[u8]) -> Result<usize, webrtc_util::Error> {
+        self.conn.send(buf).await
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize, webrtc_util::Error> {
+        self.conn.send_to(buf, target).await
+    }
+
+    async fn local_addr(&self) -> Result<SocketAddr, webrtc_util::Error> {
+        self.conn.local_addr().await
+    }
+
+    async fn remote_addr(&self) -> Option<SocketAddr> {
+        self.conn.remote_addr().await
+    }
+
+    async fn close(&self) -> Result<(), webrtc_util::Error> {
+        self.conn.close().await
+    }
+}
 
 struct AuthBuilder {
     cred_map: HashMap<String, Vec<u8>>,
+    rest: Option<Arc<RestAuth>>,
 }
 
 impl AuthBuilder {
-    fn new(cred_map: HashMap<String, Vec<u8>>) -> Self {
-        Self { cred_map }
+    fn new(cred_map: HashMap<String, Vec<u8>>, rest: Option<Arc<RestAuth>>) -> Self {
+        Self { cred_map, rest }
     }
 }
 
@@ -27,44 +191,151 @@ impl AuthHandler for AuthBuilder {
     fn auth_handle(
         &self,
         username: &str,
-        _realm: &str,
-        _src_addr: SocketAddr,
+        realm: &str,
+        src_addr: SocketAddr,
     ) -> Result<Vec<u8>, Error> {
         if let Some(pw) = self.cred_map.get(username) {
-            Ok(pw.to_vec())
-        } else {
-            Err(Error::ErrFakeErr)
+            return Ok(pw.to_vec());
+        }
+        match &self.rest {
+            Some(rest) => {
+                let now = (utils::get_epoch_ms() / 1000) as u64;
+                let password = rest.password(username, src_addr, now)?;
+                Ok(generate_auth_key(username, realm, &password))
+            }
+            None => Err(Error::ErrFakeErr),
         }
     }
 }
 
-/// Run a udp turn server.
+/// Run a turn server on udp, and on tcp and tls if their ports are set.
+/// Besides the static username and password, credentials of TURN REST API are accepted
+/// if `secret` is set, and each user of them can relay from at most `user_quota` addresses.
 /// more about turn server: https://docs.rs/turn/latest/turn/server/struct.Server.html
-pub async fn run_udp_turn(
-    public_ip: &str,
-    port: u16,
-    username: &str,
-    password: &str,
-    realm: &str,
-) -> Result<Server, Error> {
-    let conn = Arc::new(UdpSocket::bind(format!("0.0.0.0:{}", port)).await?);
+pub async fn run_turn(config: &TurnConfig) -> Result<Server, Error> {
+    let mut conns: Vec<Arc<dyn Conn + Send + Sync>> = vec![Arc::new(
+        UdpSocket::bind(format!("0.0.0.0:{}", config.port)).await?,
+    )];
+    if let Some(port) = config.tcp_port {
+        conns.push(Arc::new(StreamConn::bind(port, None).await?));
+    }
+    if let Some(port) = config.tls_port {
+        conns.push(Arc::new(
+            StreamConn::bind(port, Some(tls_acceptor(config)?)).await?,
+        ));
+    }
     let mut cred_map = HashMap::new();
-    let auth = generate_auth_key(username, realm, password);
-    cred_map.insert(username.to_owned(), auth);
+    let auth = generate_auth_key(&config.username, &config.realm, &config.password);
+    cred_map.insert(config.username.to_owned(), auth);
+    let rest = config.secret.as_ref().map(|secret| {
+        Arc::new(RestAuth::new(
+            secret,
+            config.user_quota,
+            config.credential_ttl,
+        ))
+    });
+    if let Some(auth) = rest.as_ref() {
+        conns = conns
+            .into_iter()
+            .map(|conn| {
+                Arc::new(RestAuthConn {
+                    conn,
+                    auth: auth.clone(),
+                }) as Arc<dyn Conn + Send + Sync>
+            })
+            .collect();
+    }
 
-    let server = Server::new(ServerConfig {
-        conn_configs: vec![ConnConfig {
+    let relay_address = IpAddr::from_str(&config.public_ip)?;
+    let conn_configs = conns
+        .into_iter()
+        .map(|conn| ConnConfig {
             conn,
             relay_addr_generator: Box::new(RelayAddressGeneratorStatic {
-                relay_address: IpAddr::from_str(public_ip)?,
+                relay_address,
                 address: "0.0.0.0".to_owned(),
                 net: Arc::new(Net::new(None)),
             }),
-        }],
-        realm: realm.to_owned(),
-        auth_handler: Arc::new(AuthBuilder::new(cred_map)),
+        })
+        .collect();
+
+    let server = Server::new(ServerConfig {
+        conn_configs,
+        realm: config.realm.to_owned(),
+        auth_handler: Arc::new(AuthBuilder::new(cred_map, rest)),
         channel_bind_timeout: Duration::from_secs(0),
     })
     .await?;
     Ok(server)
 }
+
+/// Load certificate chain and PKCS #8 private key of tls, both in PEM.
+fn tls_acceptor(config: &TurnConfig) -> Result<tokio_native_tls::TlsAcceptor, Error> {
+    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => (fs::read(cert)?, fs::read(key)?),
+        _ => return Err(Error::Other("tls_cert and tls_key are required".to_owned())),
+    };
+    let identity = native_tls::Identity::from_pkcs8(&cert, &key)
+        .map_err(|e| Error::Other(format!("invalid tls certificate: {}", e)))?;
+    let acceptor = native_tls::TlsAcceptor::new(identity)
+        .map_err(|e| Error::Other(format!("create tls acceptor failed: {}", e)))?;
+    Ok(acceptor.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A request signed with credential of `username` from `password`.
+    fn request(username: &str, password: &str) -> Vec<u8> {
+        use stun::message::Setter;
+        use stun::message::BINDING_REQUEST;
+        use stun::textattrs::Realm;
+        use stun::textattrs::Username;
+
+        let mut m = Message::new();
+        let key = generate_auth_key(username, "rings", password);
+        let attrs: Vec<Box<dyn Setter>> = vec![
+            Box::new(BINDING_REQUEST),
+            Box::new(Username::new(ATTR_USERNAME, username.to_owned())),
+            Box::new(Realm::new(ATTR_REALM, "rings".to_owned())),
+            Box::new(MessageIntegrity(key)),
+        ];
+        m.build(&attrs).unwrap();
+        m.raw
+    }
+
+    #[test]
+    fn test_rest_auth() {
+        let auth = RestAuth::new("secret", 2, 1000);
+        let addr = |port| SocketAddr::from(([10, 0, 0, 1], port));
+        let username = "1000:0x11e807fcc88dd319270493fb2e822e388fe36ab0";
+        let password = rest::credential("secret", username);
+        let admit = |username, port, now| auth.admit(username, addr(port), now);
+
+        // addresses are admitted only after they are authenticated
+        assert!(auth.password(username, addr(1), 999).is_err());
+        assert!(auth
+            .authenticate(&request(username, "forged"), addr(1), 999)
+            .is_err());
+        assert!(auth.password(username, addr(1), 999).is_err());
+        auth.authenticate(&request(username, &password), addr(1), 999)
+            .unwrap();
+        assert_eq!(auth.password(username, addr(1), 999).unwrap(), password);
+        assert!(auth.password(username, addr(1), 1000).is_err());
+        assert!(auth.password("rings", addr(1), 999).is_err());
+
+        // at most 2 addresses of a user, until their credentials expire
+        assert!(admit(username, 2, 999).is_ok());
+        assert!(admit(username, 1, 999).is_ok());
+        assert!(admit(username, 3, 999).is_err());
+        let renewed = "2000:0x11e807fcc88dd319270493fb2e822e388fe36ab0";
+        assert!(admit(renewed, 3, 1500).is_ok());
+        assert!(admit("2000:0x00", 4, 1500).is_ok());
+
+        // credential expiring later than ttl is forged
+        assert!(admit("3000:0x00", 5, 1500).is_err());
+        let far = "9999999999:0x11e807fcc88dd319270493fb2e822e388fe36ab0";
+        assert!(auth.password(far, addr(1), 1500).is_err());
+    }
+}
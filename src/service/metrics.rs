@@ -0,0 +1,192 @@
+//! Render metrics of node in Prometheus text format.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::prelude::rings_core::message::MessageHandler;
+use crate::prelude::rings_core::swarm::Swarm;
+use crate::prelude::rings_core::swarm::TransportManager;
+use crate::prelude::rings_core::types::ice_transport::IceTransport;
+
+/// Content type of Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn write_single(out: &mut String, name: &str, kind: &str, help: &str, value: impl ToString) {
+    write_header(out, name, kind, help);
+    writeln!(out, "{} {}", name, value.to_string()).unwrap();
+}
+
+/// Collect counters of swarm and gauges of transports and dht.
+pub async fn render(swarm: &Arc<Swarm>, msg_handler: &Arc<MessageHandler>) -> String {
+    let m = swarm.metrics().snapshot();
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "rings_messages_handled_total",
+        "counter",
+        "Messages handled, by message type.",
+    );
+    for (kind, count) in m.messages_handled.iter() {
+        writeln!(
+            out,
+            "rings_messages_handled_total{{type=\"{}\"}} {}",
+            kind, count
+        )
+        .unwrap();
+    }
+    write_single(
+        &mut out,
+        "rings_relay_transponds_total",
+        "counter",
+        "Payloads transponded to next hop.",
+        m.relay_transponds,
+    );
+    write_single(
+        &mut out,
+        "rings_verify_failures_total",
+        "counter",
+        "Received payloads which failed verification or expired.",
+        m.verify_failures,
+    );
+    write_single(
+        &mut out,
+        "rings_encode_errors_total",
+        "counter",
+        "Payloads failed to encode before sending.",
+        m.encode_errors,
+    );
+    write_single(
+        &mut out,
+        "rings_decode_errors_total",
+        "counter",
+        "Received data failed to decode as payload.",
+        m.decode_errors,
+    );
+    write_single(
+        &mut out,
+        "rings_stabilization_rounds_total",
+        "counter",
+        "Rounds of stabilization.",
+        m.stabilization_rounds,
+    );
+
+    let mut states = BTreeMap::new();
+    for (_, transport) in swarm.get_transports() {
+        let state = transport
+            .ice_connection_state()
+            .await
+            .map(|s| format!("{:?}", s))
+            .unwrap_or_else(|| "None".to_owned());
+        *states.entry(state).or_insert(0) += 1;
+    }
+    write_header(
+        &mut out,
+        "rings_transports",
+        "gauge",
+        "Registered transports, by ice connection state.",
+    );
+    for (state, count) in states.iter() {
+        writeln!(out, "rings_transports{{state=\"{}\"}} {}", state, count).unwrap();
+    }
+    let pendings = swarm
+        .pending_transports()
+        .await
+        .map(|p| p.len())
+        .unwrap_or(0);
+    write_single(
+        &mut out,
+        "rings_pending_transports",
+        "gauge",
+        "Transports waiting for handshake.",
+        pendings,
+    );
+
+    let dht = msg_handler.dht_snapshot().await;
+    write_single(
+        &mut out,
+        "rings_dht_storage_vnodes",
+        "gauge",
+        "Virtual nodes stored on local dht.",
+        dht.storage.len(),
+    );
+    write_single(
+        &mut out,
+        "rings_dht_cache_vnodes",
+        "gauge",
+        "Virtual nodes in local cache.",
+        dht.cache.len(),
+    );
+
+    write_header(
+        &mut out,
+        "rings_lookup_latency_ms",
+        "histogram",
+        "Latency of remote vnode lookups in milliseconds.",
+    );
+    for (le, count) in m.lookup_latency_buckets.iter() {
+        writeln!(
+            out,
+            "rings_lookup_latency_ms_bucket{{le=\"{}\"}} {}",
+            le, count
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "rings_lookup_latency_ms_bucket{{le=\"+Inf\"}} {}",
+        m.lookup_latency_count
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "rings_lookup_latency_ms_sum {}",
+        m.lookup_latency_sum_ms
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "rings_lookup_latency_ms_count {}",
+        m.lookup_latency_count
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use futures::lock::Mutex;
+
+    use super::*;
+    use crate::prelude::rings_core::dht::PeerRing;
+    use crate::prelude::SecretKey;
+    use crate::prelude::SessionManager;
+
+    #[tokio::test]
+    async fn test_render_metrics() {
+        let key = SecretKey::random();
+        let session = SessionManager::new_with_seckey(&key).unwrap();
+        let swarm = Arc::new(Swarm::new(
+            "stun://stun.l.google.com:19302",
+            key.address(),
+            session,
+        ));
+        let dht = Arc::new(Mutex::new(PeerRing::new(key.address().into())));
+        let msg_handler = Arc::new(MessageHandler::new(dht, swarm.clone()));
+        swarm.metrics().inc_message_handled("JoinDHT");
+        swarm.metrics().inc_relay_transponds();
+
+        let text = render(&swarm, &msg_handler).await;
+        assert!(text.contains("# TYPE rings_messages_handled_total counter\n"));
+        assert!(text.contains("rings_messages_handled_total{type=\"JoinDHT\"} 1\n"));
+        assert!(text.contains("rings_relay_transponds_total 1\n"));
+        assert!(text.contains("rings_pending_transports 0\n"));
+        assert!(text.contains("rings_dht_storage_vnodes 0\n"));
+        assert!(text.contains("rings_lookup_latency_ms_bucket{le=\"+Inf\"} 0\n"));
+    }
+}
@@ -3,76 +3,212 @@
 mod http_error;
 #[cfg(feature = "daemon")]
 mod is_turn;
+mod metrics;
+mod signaling;
+#[cfg(feature = "daemon")]
+mod turn_tcp;
 
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
+use axum::extract::ws::Message as WsMessage;
+use axum::extract::ws::WebSocket;
+use axum::extract::ws::WebSocketUpgrade;
 use axum::extract::Extension;
 use axum::response::IntoResponse;
+use axum::routing::get;
 use axum::routing::post;
 use axum::Router;
+use futures::SinkExt;
+use futures::StreamExt;
 use http::header;
+use http::header::HeaderMap;
+use http::header::HeaderName;
 use http::header::HeaderValue;
+use http::Method as HttpMethod;
 #[cfg(feature = "daemon")]
-pub use is_turn::run_udp_turn;
+pub use is_turn::run_turn;
 use jsonrpc_core::MetaIoHandler;
+use jsonrpc_pubsub::PubSubHandler;
+use jsonrpc_pubsub::Session;
 use tower_http::cors::CorsLayer;
+use tower_http::cors::Origin;
 
 use self::http_error::HttpError;
-use crate::prelude::rings_core::dht::Stabilization;
+use self::signaling::Signaling;
+use crate::config::Config;
+use crate::jsonrpc::auth;
+use crate::jsonrpc::auth::AuthConfig;
+use crate::jsonrpc::auth::Permission;
 use crate::prelude::rings_core::message::MessageHandler;
 use crate::prelude::rings_core::swarm::Swarm;
 use crate::processor::Processor;
 
 /// Run a web server to handle jsonrpc request
+/// * config: bind address, authentication, CORS and signaling are read from it
+/// * processor: handles requests, with registry if it should be used
+/// * shutdown: server stops accepting requests once it's resolved
 pub async fn run_service(
-    addr: String,
-    swarm: Arc<Swarm>,
-    msg_handler: Arc<MessageHandler>,
-    stabilization: Arc<Stabilization>,
+    config: Config,
+    processor: Processor,
+    shutdown: impl Future<Output = ()>,
 ) -> anyhow::Result<()> {
-    let binding_addr = addr.parse().unwrap();
+    let addr = config.http_addr.as_str();
+    let binding_addr: SocketAddr = addr.parse()?;
+    let cors = cors_layer(&config.rpc.cors_origins)?;
+    let auth_layer = Extension(Arc::new(config.auth_config()?));
 
+    let swarm = processor.swarm.clone();
     let swarm_layer = Extension(swarm.clone());
-    let msg_handler_layer = Extension(msg_handler.clone());
-    let stabilization_layer = Extension(stabilization.clone());
+    let msg_handler_layer = Extension(processor.msg_handler.clone());
+    let processor_layer = Extension(processor.clone());
 
     let mut jsonrpc_handler: MetaIoHandler<Processor> = MetaIoHandler::default();
     crate::jsonrpc::build_handler(&mut jsonrpc_handler).await;
     let jsonrpc_handler_layer = Extension(Arc::new(jsonrpc_handler));
+    let pubsub_handler_layer = Extension(Arc::new(crate::jsonrpc::build_pubsub_handler(
+        swarm.clone(),
+    )));
 
-    let axum_make_service = Router::new()
+    let mut router = Router::new()
         .route(
             "/",
             post(jsonrpc_io_handler)
-                .layer(&swarm_layer)
-                .layer(&msg_handler_layer)
-                .layer(&stabilization_layer)
+                .layer(&processor_layer)
                 .layer(&jsonrpc_handler_layer),
         )
-        .layer(CorsLayer::permissive())
-        .into_make_service();
+        .route("/ws", get(ws_handler).layer(&pubsub_handler_layer))
+        .route(
+            "/metrics",
+            get(metrics_handler)
+                .layer(&swarm_layer)
+                .layer(&msg_handler_layer),
+        )
+        .layer(auth_layer)
+        .layer(cors);
+    if config.signaling.enabled {
+        let signaling = Signaling::new(config.signaling.token.clone(), config.signaling.rate_limit);
+        router = router.merge(signaling::router(signaling, processor));
+    }
+    let axum_make_service = router.into_make_service_with_connect_info::<SocketAddr>();
 
     println!("Server listening on http://{}", addr);
     axum::Server::bind(&binding_addr)
         .serve(axum_make_service)
+        .with_graceful_shutdown(shutdown)
         .await?;
     Ok(())
 }
 
+/// Wait for SIGINT or SIGTERM, used as `shutdown` of [run_service].
+pub async fn wait_for_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::SignalKind;
+        let mut terminate = tokio::signal::unix::signal(SignalKind::terminate())?;
+        tokio::select! {
+            r = tokio::signal::ctrl_c() => r?,
+            _ = terminate.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+fn cors_layer(origins: &[String]) -> anyhow::Result<CorsLayer> {
+    if origins.is_empty() {
+        return Ok(CorsLayer::permissive());
+    }
+    let origins = origins
+        .iter()
+        .map(|o| HeaderValue::from_str(o))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(Origin::list(origins))
+        .allow_methods(vec![HttpMethod::GET, HttpMethod::POST])
+        .allow_headers(vec![
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static(auth::ADDRESS_HEADER),
+            HeaderName::from_static(auth::TIMESTAMP_HEADER),
+            HeaderName::from_static(auth::SIGNATURE_HEADER),
+        ]))
+}
+
 async fn jsonrpc_io_handler(
-    body: String,
-    Extension(swarm): Extension<Arc<Swarm>>,
-    Extension(msg_handler): Extension<Arc<MessageHandler>>,
-    Extension(stabilization): Extension<Arc<Stabilization>>,
+    headers: HeaderMap,
+    Extension(auth): Extension<Arc<AuthConfig>>,
+    Extension(processor): Extension<Processor>,
     Extension(io_handler): Extension<Arc<MetaIoHandler<Processor>>>,
+    body: String,
 ) -> Result<JsonResponse, HttpError> {
+    auth.authorize(&headers, &body, auth::required_permission(&body))?;
     let r = io_handler
-        .handle_request(&body, (swarm, msg_handler, stabilization).into())
+        .handle_request(&body, processor)
         .await
         .ok_or(HttpError::BadRequest)?;
     Ok(JsonResponse(r))
 }
 
+/// Serve metrics in Prometheus text format.
+async fn metrics_handler(
+    headers: HeaderMap,
+    Extension(auth): Extension<Arc<AuthConfig>>,
+    Extension(swarm): Extension<Arc<Swarm>>,
+    Extension(msg_handler): Extension<Arc<MessageHandler>>,
+) -> Result<impl IntoResponse, HttpError> {
+    auth.authorize(&headers, "", Permission::Read)?;
+    Ok((
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        metrics::render(&swarm, &msg_handler).await,
+    ))
+}
+
+/// Serve subscriptions of jsonrpc pubsub over websocket.
+/// Subscriptions only read events, so `Read` permission is checked once on upgrade.
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Extension(auth): Extension<Arc<AuthConfig>>,
+    Extension(pubsub_handler): Extension<Arc<PubSubHandler<Arc<Session>>>>,
+) -> Result<impl IntoResponse, HttpError> {
+    auth.authorize(&headers, "", Permission::Read)?;
+    Ok(ws.on_upgrade(move |socket| handle_ws_socket(socket, pubsub_handler)))
+}
+
+async fn handle_ws_socket(socket: WebSocket, pubsub_handler: Arc<PubSubHandler<Arc<Session>>>) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let (tx, mut rx) = futures::channel::mpsc::unbounded::<String>();
+    let session = Arc::new(Session::new(tx.clone()));
+
+    let send_task = tokio::spawn(async move {
+        while let Some(msg) = rx.next().await {
+            if ws_sender.send(WsMessage::Text(msg)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(msg)) = ws_receiver.next().await {
+        match msg {
+            WsMessage::Text(req) => {
+                if let Some(resp) = pubsub_handler.handle_request(&req, session.clone()).await {
+                    if tx.unbounded_send(resp).is_err() {
+                        break;
+                    }
+                }
+            }
+            WsMessage::Close(_) => break,
+            _ => {}
+        }
+    }
+    // dropping session will cancel all of its subscriptions
+    drop(session);
+    send_task.abort();
+}
+
 #[derive(Debug, Clone)]
 struct JsonResponse(String);
 
@@ -0,0 +1,209 @@
+//! Signaling endpoint `POST /sdp`, which answers an offer in one request.
+//!
+//! Request body is the handshake info of an offer, as created by
+//! [Processor::create_offer], and response body is the handshake info of answer,
+//! to be accepted by the offerer. Both are `text/plain`.
+//! [Processor::connect_peer_via_signaling] does all of these for the offerer.
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use axum::extract::ConnectInfo;
+use axum::extract::Extension;
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::Router;
+use http::header;
+use http::header::HeaderMap;
+use subtle::ConstantTimeEq;
+use tower_http::cors::CorsLayer;
+
+use super::http_error::HttpError;
+use crate::processor::Processor;
+
+/// Clients are forgotten when their window is over, once there are this many of them.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// Count requests of each ip in a fixed window.
+struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    hits: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a request of `ip`, return false if it's over limit.
+    fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() >= MAX_TRACKED_CLIENTS {
+            hits.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+        }
+        let (start, count) = hits.entry(ip).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= self.limit
+    }
+}
+
+/// State of signaling endpoint.
+pub struct Signaling {
+    token: Option<String>,
+    limiter: RateLimiter,
+}
+
+impl Signaling {
+    /// Create a Signaling
+    /// * token: required as `Authorization: Bearer <token>` if it's set
+    /// * rate_limit: max requests per minute from one ip
+    pub fn new(token: Option<String>, rate_limit: u32) -> Self {
+        Self {
+            token,
+            limiter: RateLimiter::new(rate_limit, Duration::from_secs(60)),
+        }
+    }
+
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), HttpError> {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let matched = given.map_or(false, |given| {
+            bool::from(given.as_bytes().ct_eq(token.as_bytes()))
+        });
+        if matched {
+            Ok(())
+        } else {
+            Err(HttpError::Unauthorized)
+        }
+    }
+}
+
+/// Routes of signaling, served with permissive CORS for browser nodes on any origin.
+/// The router should be served with `ConnectInfo<SocketAddr>`, which is used by rate limiting.
+pub fn router(signaling: Signaling, processor: Processor) -> Router {
+    Router::new()
+        .route("/sdp", post(sdp_handler))
+        .layer(Extension(Arc::new(signaling)))
+        .layer(Extension(processor))
+        .layer(CorsLayer::permissive())
+}
+
+async fn sdp_handler(
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Extension(signaling): Extension<Arc<Signaling>>,
+    Extension(processor): Extension<Processor>,
+    body: String,
+) -> Result<impl IntoResponse, HttpError> {
+    if !signaling.limiter.check(remote.ip()) {
+        return Err(HttpError::TooManyRequests);
+    }
+    signaling.authorize(&headers)?;
+    let (_, answer) = processor.answer_offer(body.trim()).await.map_err(|e| {
+        log::warn!("signaling from {} failed: {}", remote, e);
+        HttpError::BadRequest
+    })?;
+    Ok(([(header::CONTENT_TYPE, "text/plain")], answer.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use futures::lock::Mutex as AsyncMutex;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::prelude::rings_core::dht::Stabilization;
+    use crate::prelude::rings_core::swarm::TransportManager;
+    use crate::prelude::*;
+
+    fn new_processor() -> Processor {
+        let key = SecretKey::random();
+        let session = SessionManager::new_with_seckey(&key).unwrap();
+        let swarm = Arc::new(Swarm::new(
+            "stun://stun.l.google.com:19302",
+            key.address(),
+            session,
+        ));
+        let dht = Arc::new(AsyncMutex::new(PeerRing::new(key.address().into())));
+        let msg_handler = Arc::new(MessageHandler::new(dht.clone(), swarm.clone()));
+        let stabilization = Arc::new(Stabilization::new(dht, swarm.clone(), 20));
+        (swarm, msg_handler, stabilization).into()
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(limiter.check(a));
+        assert!(limiter.check(a));
+        assert!(!limiter.check(a));
+        assert!(limiter.check(b));
+
+        let limiter = RateLimiter::new(1, Duration::from_millis(0));
+        assert!(limiter.check(a));
+        assert!(limiter.check(a));
+    }
+
+    #[tokio::test]
+    async fn test_connect_via_signaling() {
+        let answerer = new_processor();
+        let offerer = new_processor();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sdp", listener.local_addr().unwrap());
+        let app = router(
+            Signaling::new(Some("token".to_owned()), 2),
+            answerer.clone(),
+        );
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>()),
+        );
+
+        assert!(matches!(
+            offerer.connect_peer_via_signaling(&url, None).await,
+            Err(Error::Signaling(_))
+        ));
+        offerer
+            .connect_peer_via_signaling(&url, Some("token"))
+            .await
+            .unwrap();
+        assert!(offerer
+            .swarm
+            .get_transport(&answerer.swarm.address())
+            .is_some());
+        assert!(answerer
+            .swarm
+            .get_transport(&offerer.swarm.address())
+            .is_some());
+
+        // over rate limit
+        assert!(matches!(
+            offerer
+                .connect_peer_via_signaling(&url, Some("token"))
+                .await,
+            Err(Error::Signaling(_))
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+//! TURN over TCP and TLS, as [RFC 6062](https://www.rfc-editor.org/rfc/rfc6062) between
+//! client and server.
+//!
+//! TURN server reads datagrams from a [Conn], so [StreamConn] accepts streams and splits
+//! them into STUN messages and ChannelData messages, which are framed by their length.
+//! Messages received from all streams are read from one [Conn], and replies are written
+//! back to the stream of the address they are sent to.
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::task::JoinHandle;
+use tokio_native_tls::TlsAcceptor;
+use webrtc_util::Conn;
+use webrtc_util::Error;
+
+use crate::prelude::rings_core::async_trait;
+
+/// Same as the buffer of TURN server, larger messages are rejected.
+const MAX_MESSAGE_SIZE: usize = 1500;
+/// Streams accepted after this many are closed immediately.
+const MAX_STREAMS: usize = 1024;
+const STUN_HEADER_SIZE: usize = 20;
+const CHANNEL_DATA_HEADER_SIZE: usize = 4;
+
+type Writers = Arc<Mutex<HashMap<SocketAddr, Stream>>>;
+
+struct Stream {
+    writer: mpsc::UnboundedSender<Vec<u8>>,
+    reader: JoinHandle<()>,
+}
+
+/// A [Conn] of TCP or TLS streams accepted by a listener.
+pub(crate) struct StreamConn {
+    local_addr: SocketAddr,
+    inbound: AsyncMutex<mpsc::UnboundedReceiver<(Vec<u8>, SocketAddr)>>,
+    streams: Writers,
+    acceptor: JoinHandle<()>,
+}
+
+impl StreamConn {
+    /// Listen on `port` of all interfaces, streams are wrapped by TLS if `tls` is given.
+    pub(crate) async fn bind(port: u16, tls: Option<TlsAcceptor>) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+        let local_addr = listener.local_addr()?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let streams: Writers = Default::default();
+        let acceptor = tokio::spawn(accept_loop(listener, tls, tx, streams.clone()));
+        Ok(Self {
+            local_addr,
+            inbound: AsyncMutex::new(rx),
+            streams,
+            acceptor,
+        })
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    inbound: mpsc::UnboundedSender<(Vec<u8>, SocketAddr)>,
+    streams: Writers,
+) {
+    loop {
+        let (tcp, addr) = match listener.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("turn accept failed: {}", e);
+                continue;
+            }
+        };
+        if streams.lock().unwrap().len() >= MAX_STREAMS {
+            log::warn!("turn streams are over limit, reject {}", addr);
+            continue;
+        }
+        let inbound = inbound.clone();
+        let streams = streams.clone();
+        let tls = tls.clone();
+        // handshake of tls should not block accepting
+        tokio::spawn(async move {
+            match tls {
+                Some(tls) => match tls.accept(tcp).await {
+                    Ok(stream) => serve(stream, addr, inbound, streams),
+                    Err(e) => log::debug!("turn tls handshake with {} failed: {}", addr, e),
+                },
+                None => serve(tcp, addr, inbound, streams),
+            }
+        });
+    }
+}
+
+/// Register writer of stream, and forward messages read from it to `inbound`.
+fn serve<S>(
+    stream: S,
+    addr: SocketAddr,
+    inbound: mpsc::UnboundedSender<(Vec<u8>, SocketAddr)>,
+    streams: Writers,
+) where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut rd, mut wr) = tokio::io::split(stream);
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(buf) = rx.recv().await {
+            if wr.write_all(&buf).await.is_err() {
+                break;
+            }
+        }
+        wr.shutdown().await.ok();
+    });
+    // reader removes the stream when it's closed, which should happen after inserting
+    let mut registered = streams.lock().unwrap();
+    let streams_1 = streams.clone();
+    let reader = tokio::spawn(async move {
+        loop {
+            match read_message(&mut rd).await {
+                Ok(msg) => {
+                    if inbound.send((msg, addr)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    log::debug!("turn stream of {} closed: {}", addr, e);
+                    break;
+                }
+            }
+        }
+        streams_1.lock().unwrap().remove(&addr);
+    });
+    registered.insert(addr, Stream { writer: tx, reader });
+}
+
+/// Length of message and padding after it, by its header.
+fn message_len(header: &[u8; 4]) -> io::Result<(usize, usize)> {
+    let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+    match header[0] {
+        // STUN messages are always padded to 4 bytes
+        0x00..=0x3f => Ok((STUN_HEADER_SIZE + len, 0)),
+        // ChannelData, padded to 4 bytes over streams
+        0x40..=0x7f => Ok((CHANNEL_DATA_HEADER_SIZE + len, (4 - len % 4) % 4)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "neither STUN nor ChannelData",
+        )),
+    }
+}
+
+async fn read_message<R: AsyncRead + Unpin>(rd: &mut R) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; 4];
+    rd.read_exact(&mut header).await?;
+    let (len, padding) = message_len(&header)?;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too large",
+        ));
+    }
+    let mut msg = vec![0u8; len + padding];
+    msg[..4].copy_from_slice(&header);
+    rd.read_exact(&mut msg[4..]).await?;
+    msg.truncate(len);
+    Ok(msg)
+}
+
+#[async_trait]
+impl Conn for StreamConn {
+    async fn connect(&self, _addr: SocketAddr) -> Result<(), Error> {
+        Err(Error::Other("connect is not supported".to_owned()))
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.recv_from(buf).await.map(|(n, _)| n)
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        let (msg, addr) = self
+            .inbound
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(Error::ErrClosedListener)?;
+        let n = msg.len().min(buf.len());
+        buf[..n].copy_from_slice(&msg[..n]);
+        Ok((n, addr))
+    }
+
+    async fn send(&self, _buf: &[u8]) -> Result<usize, Error> {
+        Err(Error::Other(
+            "send without address is not supported".to_owned(),
+        ))
+    }
+
+    async fn send_to(&self, buf: &[u8], target: SocketAddr) -> Result<usize, Error> {
+        let mut msg = buf.to_vec();
+        if let Some(header) = buf.get(..4) {
+            let (_, padding) = message_len(header.try_into().unwrap())?;
+            msg.resize(buf.len() + padding, 0);
+        }
+        let streams = self.streams.lock().unwrap();
+        let stream = streams.get(&target).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("no stream of {}", target),
+            )
+        })?;
+        stream
+            .writer
+            .send(msg)
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    async fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.local_addr)
+    }
+
+    async fn remote_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        self.acceptor.abort();
+        let streams = std::mem::take(&mut *self.streams.lock().unwrap());
+        for stream in streams.into_values() {
+            stream.reader.abort();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_message_len() {
+        // binding request without attributes
+        assert_eq!(message_len(&[0x00, 0x01, 0x00, 0x00]).unwrap(), (20, 0));
+        assert_eq!(message_len(&[0x00, 0x01, 0x00, 0x08]).unwrap(), (28, 0));
+        // ChannelData of channel 0x4000
+        assert_eq!(message_len(&[0x40, 0x00, 0x00, 0x05]).unwrap(), (9, 3));
+        assert_eq!(message_len(&[0x40, 0x00, 0x00, 0x08]).unwrap(), (12, 0));
+        assert!(message_len(&[0x80, 0x00, 0x00, 0x00]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_conn() {
+        let conn = StreamConn::bind(0, None).await.unwrap();
+        let port = conn.local_addr().await.unwrap().port();
+        let mut client = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        // a binding request, and a ChannelData with padding
+        let stun = [[0x00, 0x01, 0x00, 0x00].as_slice(), &[7u8; 16]].concat();
+        let channel_data = [0x40, 0x00, 0x00, 0x01, 0xff, 0, 0, 0];
+        client
+            .write_all(&[stun.as_slice(), &channel_data].concat())
+            .await
+            .unwrap();
+
+        let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
+        let (n, addr) = conn.recv_from(&mut buf).await.unwrap();
+        assert_eq!(addr, client_addr);
+        assert_eq!(&buf[..n], stun.as_slice());
+        let (n, _) = conn.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], &channel_data[..5]);
+
+        // padding is added to ChannelData sent to client
+        conn.send_to(&channel_data[..5], client_addr).await.unwrap();
+        let mut reply = [0u8; 8];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, channel_data);
+
+        conn.close().await.unwrap();
+        assert!(conn.send_to(&stun, client_addr).await.is_err());
+    }
+}
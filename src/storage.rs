@@ -0,0 +1,91 @@
+#![warn(missing_docs)]
+//! Records of DHT kept across restarts, in the database configured by [StorageConfig].
+//!
+//! The DHT stores records in memory, they are restored from database on start,
+//! and saved back on shutdown.
+use crate::config::StorageConfig;
+use crate::error::Error;
+use crate::error::Result;
+use crate::prelude::rings_core::dht::vnode::VirtualNode;
+use crate::prelude::rings_core::storage::PersistenceStorageOperation;
+use crate::prelude::rings_core::storage::PersistenceStorageReadAndWrite;
+use crate::prelude::rings_core::storage::Storage;
+use crate::prelude::PeerRing;
+
+/// Database of DHT records.
+pub struct DhtStorage {
+    db: Storage,
+}
+
+impl DhtStorage {
+    /// Open database at `config.path`, with `config.capacity` bytes of cache.
+    pub async fn open(config: &StorageConfig) -> Result<Self> {
+        let db = Storage::new_with_cap_and_path(config.capacity, &config.path)
+            .await
+            .map_err(Error::Storage)?;
+        Ok(Self { db })
+    }
+
+    /// Load saved records into storage of `dht`, returns number of records.
+    pub async fn restore(&self, dht: &PeerRing) -> Result<usize> {
+        let records: Vec<(String, VirtualNode)> =
+            self.db.get_all().await.map_err(Error::Storage)?;
+        for (_, vnode) in records.iter() {
+            dht.storage.set(&vnode.did(), vnode.clone());
+        }
+        Ok(records.len())
+    }
+
+    /// Replace saved records by storage of `dht` and flush them to disk,
+    /// returns number of records.
+    pub async fn save(&self, dht: &PeerRing) -> Result<usize> {
+        let records = dht.storage.values();
+        self.db.clear().await.map_err(Error::Storage)?;
+        for vnode in records.iter() {
+            self.db
+                .put(&format!("{:?}", *vnode.did()), vnode)
+                .await
+                .map_err(Error::Storage)?;
+        }
+        self.db.flush().await.map_err(Error::Storage)?;
+        Ok(records.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::prelude::rings_core::dht::Did;
+    use crate::prelude::uuid;
+    use crate::prelude::SecretKey;
+
+    #[tokio::test]
+    async fn test_save_and_restore_dht() {
+        let config = StorageConfig {
+            path: PathBuf::from(format!("temp/storage-{}", uuid::Uuid::new_v4())),
+            capacity: 4096,
+        };
+        let did: Did = SecretKey::random().address().into();
+        let vnode: VirtualNode = "hello".to_string().try_into().unwrap();
+
+        let dht = PeerRing::new(did);
+        dht.storage.set(&vnode.did(), vnode.clone());
+        let storage = DhtStorage::open(&config).await.unwrap();
+        assert_eq!(storage.save(&dht).await.unwrap(), 1);
+        drop(storage);
+
+        let dht = PeerRing::new(did);
+        let storage = DhtStorage::open(&config).await.unwrap();
+        assert_eq!(storage.restore(&dht).await.unwrap(), 1);
+        assert_eq!(dht.storage.get(&vnode.did()), Some(vnode.clone()));
+
+        // records gone from dht are removed on next save
+        dht.storage.remove(&vnode.did());
+        assert_eq!(storage.save(&dht).await.unwrap(), 0);
+        let dht = PeerRing::new(did);
+        assert_eq!(storage.restore(&dht).await.unwrap(), 0);
+        std::fs::remove_dir_all(&config.path).ok();
+    }
+}
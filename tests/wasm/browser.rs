@@ -1,9 +1,17 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use js_sys::Uint8Array;
 use rings_node::browser;
 use rings_node::browser::Peer;
 use rings_node::browser::SignerMode;
 use rings_node::browser::TransportAndIce;
+use rings_node::jsonrpc::response::FetchedValue;
+use rings_node::jsonrpc::response::SubRingInfo;
+use rings_node::prelude::js_sys;
 use rings_node::prelude::rings_core::prelude::web3::contract::tokens::Tokenizable;
+use rings_node::prelude::wasm_bindgen::prelude::*;
+use rings_node::prelude::wasm_bindgen::JsCast;
 use rings_node::prelude::wasm_bindgen_futures::JsFuture;
 use rings_node::prelude::*;
 use wasm_bindgen_test::*;
@@ -21,7 +29,7 @@ fn new_client() -> browser::Client {
     let auth = unsigned_info.auth().ok().unwrap();
     let signed_data = Uint8Array::from(key.sign(&auth).to_vec().as_slice());
     let stuns = "stun://stun.l.google.com:19302".to_owned();
-    browser::Client::new(&unsigned_info, signed_data, stuns)
+    browser::Client::new(&unsigned_info, signed_data, stuns, None)
         .ok()
         .unwrap()
 }
@@ -90,3 +98,120 @@ async fn test_two_client_connect_and_list() {
     let peers = get_peers(&client1).await;
     assert_eq!(peers.len(), 0);
 }
+
+#[wasm_bindgen_test]
+async fn test_store_fetch_and_subring() {
+    let client1 = new_client();
+    let client2 = new_client();
+    futures::try_join!(
+        JsFuture::from(client1.start()),
+        JsFuture::from(client2.start()),
+    )
+    .unwrap();
+    create_connection(&client1, &client2).await;
+    JsFuture::from(client1.wait_for_data_channel_open(client2.address()))
+        .await
+        .unwrap();
+
+    let did = JsFuture::from(client1.store("hello rings".to_owned()))
+        .await
+        .unwrap()
+        .as_string()
+        .unwrap();
+    let fetched = JsFuture::from(client2.fetch(did.clone())).await.unwrap();
+    let fetched: FetchedValue = fetched.into_serde().unwrap();
+    assert_eq!(fetched.did, did);
+    assert_eq!(fetched.data, vec!["hello rings".to_owned()]);
+
+    let subring = JsFuture::from(client1.create_subring("browser".to_owned()))
+        .await
+        .unwrap()
+        .as_string()
+        .unwrap();
+    JsFuture::from(client2.join_subring("browser".to_owned()))
+        .await
+        .unwrap();
+    fluvio_wasm_timer::Delay::new(std::time::Duration::from_secs(2))
+        .await
+        .unwrap();
+    let mut rings = vec![];
+    for client in [&client1, &client2] {
+        let r: Vec<SubRingInfo> = JsFuture::from(client.list_subrings())
+            .await
+            .unwrap()
+            .into_serde()
+            .unwrap();
+        rings.extend(r);
+    }
+    let ring = rings.iter().find(|r| r.did == subring).unwrap();
+    assert_eq!(ring.name, "browser");
+    assert!(!ring.members.is_empty(), "joined member should be listed");
+}
+
+#[wasm_bindgen_test]
+async fn test_event_listeners() {
+    let client1 = new_client();
+    let client2 = new_client();
+    futures::try_join!(
+        JsFuture::from(client1.start()),
+        JsFuture::from(client2.start()),
+    )
+    .unwrap();
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let add_listener = |client: &browser::Client, event: &str| {
+        let events = events.clone();
+        let listener = Closure::wrap(Box::new(move |ev: JsValue| {
+            events.borrow_mut().push(ev);
+        }) as Box<dyn FnMut(JsValue)>);
+        let id = client
+            .add_event_listener(
+                JsValue::from_str(event).unchecked_into(),
+                listener.as_ref().clone().unchecked_into(),
+            )
+            .ok()
+            .unwrap();
+        listener.forget();
+        id
+    };
+    let connected = add_listener(&client1, "peerConnected");
+    add_listener(&client1, "message");
+    assert!(client1
+        .add_event_listener(
+            JsValue::from_str("unknown").unchecked_into(),
+            js_sys::Function::new_no_args("").unchecked_into(),
+        )
+        .is_err());
+
+    create_connection(&client1, &client2).await;
+    JsFuture::from(client1.wait_for_data_channel_open(client2.address()))
+        .await
+        .unwrap();
+    JsFuture::from(client2.wait_for_data_channel_open(client1.address()))
+        .await
+        .unwrap();
+    JsFuture::from(client2.send_message(client1.address(), Uint8Array::from(b"hello".as_slice())))
+        .await
+        .unwrap();
+    fluvio_wasm_timer::Delay::new(std::time::Duration::from_secs(2))
+        .await
+        .unwrap();
+
+    let field = |ev: &JsValue, k: &str| js_sys::Reflect::get(ev, &JsValue::from_str(k)).unwrap();
+    let received = events.borrow().clone();
+    let connected_ev = received
+        .iter()
+        .find(|ev| field(ev, "type") == "peerConnected")
+        .expect("peerConnected should be dispatched");
+    assert_eq!(field(connected_ev, "address"), client2.address());
+    let message_ev = received
+        .iter()
+        .find(|ev| field(ev, "type") == "message")
+        .expect("message should be dispatched");
+    assert_eq!(field(message_ev, "from"), client2.address());
+    let data: Uint8Array = field(message_ev, "data").unchecked_into();
+    assert_eq!(data.to_vec(), b"hello".to_vec());
+
+    assert!(client1.remove_event_listener(connected));
+    assert!(!client1.remove_event_listener(connected));
+}